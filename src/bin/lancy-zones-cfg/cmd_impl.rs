@@ -1,98 +1,141 @@
-use std::{fs, path::Path, rc::Rc};
+use std::{path::Path, rc::Rc};
 
-use clap::error;
 use x11rb::{connection::Connection, protocol::xproto::Screen, rust_connection::RustConnection};
 
 use lancy_zones::{
-    config::{self, load_cfg_file},
+    config::{self, ConfigError, Desktop, Length, OffsetError},
+    layout::{self, CellSpan, LayoutError, Template},
+    state::{self, State},
     util,
 };
 
-fn make_conn() -> (Rc<RustConnection>, Rc<Screen>) {
-    let (conn, screen_num) = x11rb::connect(None).unwrap();
+pub type CmdResult = Result<(), ConfigError>;
+
+fn make_conn() -> Result<(Rc<RustConnection>, Rc<Screen>), ConfigError> {
+    let (conn, screen_num) = x11rb::connect(None)?;
     let screen = &conn.setup().roots[screen_num];
     let screen = Rc::new(screen.to_owned());
     let conn = Rc::new(conn);
-    (conn, screen)
+    Ok((conn, screen))
 }
 
-fn get_monitor_by_name(monitor_name: &str) -> config::Monitor {
-    let (conn, screen) = make_conn();
-    let monitors = util::get_monitors(&conn, screen.root).expect("Could not fetch monitors");
-    monitors
-        .iter()
-        .find(|monitor| -> bool { monitor.name == monitor_name })
-        .cloned()
-        .expect(&std::format!(
-            "Could not find monitor with name {}",
-            monitor_name
-        ))
+fn get_monitor_config_mut<'a>(
+    config: &'a mut config::Config,
+    config_name: &str,
+) -> Result<&'a mut config::MonitorConfig, ConfigError> {
+    config
+        .get_monitor_config_mut(config_name)
+        .ok_or_else(|| ConfigError::MissingLayout(config_name.to_string()))
 }
 
-fn get_monitor_of_assigned_config<'a>(
+fn get_monitor_mut<'a>(
     config: &'a mut config::Config,
+    monitor_name: &str,
+) -> Result<&'a mut config::Monitor, ConfigError> {
+    config
+        .monitors
+        .iter_mut()
+        .find(|monitor| -> bool { monitor.name == monitor_name })
+        .ok_or_else(|| ConfigError::MissingMonitor(monitor_name.to_string()))
+}
+
+/// Size of the monitor a layout is generated for: `monitor_name` if given, otherwise the
+/// first monitor the config is assigned to.
+fn get_target_monitor_size(
+    config: &config::Config,
     config_name: &str,
-) -> Option<&'a mut config::Monitor> {
-    config.monitors.iter_mut().find(|monitor| -> bool {
-        if let Some(cfg) = &monitor.config {
-            cfg.name == config_name
-        } else {
-            false
-        }
-    })
+    monitor_name: Option<&str>,
+) -> Result<(u16, u16), ConfigError> {
+    let monitor = match monitor_name {
+        Some(monitor_name) => config
+            .monitors
+            .iter()
+            .find(|monitor| -> bool { monitor.name == monitor_name })
+            .ok_or_else(|| ConfigError::MissingMonitor(monitor_name.to_string()))?,
+        None => config
+            .monitors_using(config_name)
+            .next()
+            .ok_or_else(|| ConfigError::Unassigned(config_name.to_string()))?,
+    };
+    Ok((monitor.width, monitor.height))
 }
 
-pub fn list_cmd() {
-    println!("{}", config::load_cfg_file());
+pub fn list_cmd(path: &Path) -> CmdResult {
+    println!("{}", config::load_cfg_file(path)?);
+    Ok(())
 }
 
-pub fn reinit_cmd() {
-    let path = config::get_config_path();
-    if path.exists() {
-        fs::remove_file(&path).expect(&std::format!(
-            "Could not delete exisiting config file at {}",
-            path.to_str().unwrap()
-        ));
+pub fn validate_cmd(path: &Path) -> CmdResult {
+    let diagnostics = config::load_cfg_file(path)?.validate();
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+
+    if diagnostics.iter().any(|d| d.is_error()) {
+        Err(ConfigError::Invalid(diagnostics))
+    } else {
+        if diagnostics.is_empty() {
+            println!("{} is valid", path.display());
+        }
+        Ok(())
     }
-    let (conn, screen) = make_conn();
-    config::init_cfg_file(&conn, screen.root);
 }
 
-pub fn create_config_cmd(config_name: &str) {
-    let mut config = config::load_cfg_file();
-    match config.get_monitor_config(config_name) {
-        Some(_) => panic!("{} already exists", config_name),
-        _ => {
-            let new_mc = config::MonitorConfig {
-                name: config_name.to_string(),
-                zones: vec![],
-            };
+pub fn reinit_cmd(path: &Path) -> CmdResult {
+    // saving replaces the old config atomically and keeps it as the latest backup
+    let (conn, screen) = make_conn()?;
+    config::init_cfg_file(&conn, screen.root, path)?;
+    Ok(())
+}
 
-            config.monitor_configs.push(new_mc);
-            config::save_cfg_file(&config);
-        }
+pub fn create_config_cmd(path: &Path, config_name: &str) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    if config.get_monitor_config(config_name).is_some() {
+        return Err(ConfigError::LayoutExists(config_name.to_string()));
     }
+
+    let new_mc = config::MonitorConfig {
+        name: config_name.to_string(),
+        zones: vec![],
+        theme: config::ThemeOverride::default(),
+        work_area: false,
+    };
+    config.monitor_configs.push(new_mc);
+    config::save_cfg_file(path, &config)
 }
 
-pub fn remove_config_cmd(config_name: &str) {
-    let mut config = config::load_cfg_file();
-    if let Some(index) = config
+pub fn remove_config_cmd(path: &Path, config_name: &str) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    let index = config
         .monitor_configs
         .iter()
         .position(|mc| -> bool { mc.name == config_name })
-    {
-        if let Some(monitor) = get_monitor_of_assigned_config(&mut config, config_name) {
+        .ok_or_else(|| ConfigError::MissingLayout(config_name.to_string()))?;
+
+    for monitor in &mut config.monitors {
+        if monitor.config.as_deref() == Some(config_name) {
             monitor.config = None;
         }
-        config.monitor_configs.remove(index);
-    } else {
-        panic!("{} does not exist", config_name);
+        monitor.desktops.retain(|a| a.config != config_name);
+    }
+    for profile in &mut config.profiles {
+        profile.assignments.retain(|_, name| name != config_name);
     }
-    config::save_cfg_file(&config);
+    config.rules.retain(|rule| rule.config != config_name);
+    config.monitor_configs.remove(index);
+    config::save_cfg_file(path, &config)
 }
 
-pub fn add_zone_cmd(config_name: &str, zone_name: &str, x: i16, y: i16, width: i16, height: i16) {
-    let mut config = config::load_cfg_file();
+pub fn add_zone_cmd(
+    path: &Path,
+    config_name: &str,
+    zone_name: &str,
+    x: Length,
+    y: Length,
+    width: Length,
+    height: Length,
+) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
     let new_zone = config::Zone {
         name: zone_name.to_string(),
         x,
@@ -101,63 +144,283 @@ pub fn add_zone_cmd(config_name: &str, zone_name: &str, x: i16, y: i16, width: i
         height,
     };
 
-    if let Some(monitor_config) = config.get_monitor_config_mut(config_name) {
-        monitor_config.add_zone(new_zone);
-    } else {
-        panic!("{} does not exist", config_name);
+    get_monitor_config_mut(&mut config, config_name)?.add_zone(new_zone)?;
+    config::save_cfg_file(path, &config)
+}
+
+/// Replaces the zones of a config with the ones `generate` builds for the target monitor size.
+/// Configs laid out in the work area get fractions instead, the work area is smaller than
+/// the monitor and can change with the panels.
+fn replace_zones(
+    path: &Path,
+    config_name: &str,
+    monitor_name: Option<&str>,
+    generate: impl FnOnce(u16, u16) -> Result<Vec<config::Zone>, LayoutError>,
+) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    let Some(work_area) = config
+        .get_monitor_config(config_name)
+        .map(|mc| -> bool { mc.work_area })
+    else {
+        return Err(ConfigError::MissingLayout(config_name.to_string()));
+    };
+    let (width, height) = get_target_monitor_size(&config, config_name, monitor_name)?;
+    let mut zones = generate(width, height)?;
+    if work_area {
+        zones = zones
+            .iter()
+            .map(|zone| -> config::Zone { zone.to_fractions(width, height) })
+            .collect();
     }
 
-    let monitor_config = config.get_monitor_config(config_name).unwrap().clone();
-    if let Some(monitor) = get_monitor_of_assigned_config(&mut config, config_name) {
-        monitor.config = Some(monitor_config);
+    get_monitor_config_mut(&mut config, config_name)?.zones = zones;
+    config::save_cfg_file(path, &config)
+}
+
+pub fn grid_cmd(
+    path: &Path,
+    config_name: &str,
+    cols: &[u32],
+    rows: &[u32],
+    merges: &[CellSpan],
+    monitor_name: Option<&str>,
+) -> CmdResult {
+    replace_zones(path, config_name, monitor_name, |width, height| {
+        layout::grid(width, height, cols, rows, merges)
+    })
+}
+
+pub fn template_list_cmd() -> CmdResult {
+    for template in Template::ALL {
+        println!("{:<15} {}", template.name(), template.description());
     }
-    config::save_cfg_file(&config);
+    Ok(())
 }
 
-pub fn remove_zone_cmd(config_name: &str, zone_name: &str) {
-    let mut config = config::load_cfg_file();
+pub fn template_apply_cmd(
+    path: &Path,
+    config_name: &str,
+    template: Template,
+    count: Option<usize>,
+    monitor_name: Option<&str>,
+) -> CmdResult {
+    replace_zones(path, config_name, monitor_name, |width, height| {
+        template.expand(width, height, count)
+    })
 }
 
-pub fn assign_cmd(monitor_name: &str, config_name: &str) {
-    let mut config = config::load_cfg_file();
-    let monitor_config = config
-        .get_monitor_config(config_name)
-        .expect(&std::format!("{} does not exist", config_name))
-        .clone();
+pub fn work_area_cmd(path: &Path, config_name: &str, enabled: bool) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    get_monitor_config_mut(&mut config, config_name)?.work_area = enabled;
+    config::save_cfg_file(path, &config)
+}
+
+/// Saves the assignments of the connected monitors, replacing a profile of the same name.
+pub fn profile_save_cmd(path: &Path, profile_name: &str) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    let (conn, screen) = make_conn()?;
+    let connected = config.assign_stored(util::get_monitors(&*conn, screen.root)?);
+    let profile = config::Profile::from_monitors(profile_name, &connected);
+    println!("{}", profile);
+
     match config
-        .monitors
+        .profiles
         .iter_mut()
-        .find(|monitor| -> bool { monitor.name == monitor_name })
+        .find(|p| -> bool { p.name == profile_name })
     {
-        Some(monitor) => monitor.config = Some(monitor_config),
-        _ => panic!("{} does not exist", monitor_name),
+        Some(existing) => *existing = profile,
+        None => config.profiles.push(profile),
+    }
+    config::save_cfg_file(path, &config)
+}
+
+pub fn profile_list_cmd(path: &Path) -> CmdResult {
+    let config = config::load_cfg_file(path)?;
+    for profile in &config.profiles {
+        println!("{}", profile);
     }
-    config::save_cfg_file(&config);
+    Ok(())
 }
 
-pub fn unassing_cmd(monitor_name: &str) {
-    let mut config = config::load_cfg_file();
+/// Adds a rule, replacing one of the same name.
+pub fn rule_add_cmd(path: &Path, rule: config::Rule) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    let mc = config
+        .get_monitor_config(&rule.config)
+        .ok_or_else(|| ConfigError::MissingLayout(rule.config.clone()))?;
+    if mc.get_zone(&rule.zone).is_none() {
+        return Err(ConfigError::MissingZone {
+            config: rule.config,
+            zone: rule.zone,
+        });
+    }
+    rule.title_regex()?;
+    println!("{}", rule);
+
     match config
-        .monitors
+        .rules
         .iter_mut()
-        .find(|monitor| -> bool { monitor.name == monitor_name })
+        .find(|r| -> bool { r.name == rule.name })
     {
-        Some(monitor) => monitor.config = None,
-        _ => panic!("{} does not exist", monitor_name),
+        Some(existing) => *existing = rule,
+        None => config.rules.push(rule),
+    }
+    config::save_cfg_file(path, &config)
+}
+
+pub fn rule_list_cmd(path: &Path) -> CmdResult {
+    let config = config::load_cfg_file(path)?;
+    for rule in &config.rules {
+        println!("{}", rule);
     }
-    config::save_cfg_file(&config);
+    Ok(())
+}
+
+pub fn rule_remove_cmd(path: &Path, rule_name: &str) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    let index = config
+        .rules
+        .iter()
+        .position(|rule| -> bool { rule.name == rule_name })
+        .ok_or_else(|| ConfigError::MissingRule(rule_name.to_string()))?;
+    config.rules.remove(index);
+    config::save_cfg_file(path, &config)
+}
+
+/// Empties the state file, a running daemon reads it again for every window it maps.
+pub fn state_clear_cmd() -> CmdResult {
+    match state::get_state_path() {
+        Some(path) => {
+            state::save_state_file(&path, &State::default())?;
+            println!("Cleared {}", path.display());
+        }
+        None => println!("No state file, $XDG_STATE_HOME and $HOME are not set"),
+    }
+    Ok(())
+}
+
+pub fn remove_zone_cmd(path: &Path, config_name: &str, zone_name: &str) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    get_monitor_config_mut(&mut config, config_name)?.remove_zone(zone_name)?;
+    config
+        .rules
+        .retain(|rule| -> bool { rule.config != config_name || rule.zone != zone_name });
+    config::save_cfg_file(path, &config)
+}
+
+pub fn edit_zone_cmd(
+    path: &Path,
+    config_name: &str,
+    zone_name: &str,
+    x: Option<Length>,
+    y: Option<Length>,
+    width: Option<Length>,
+    height: Option<Length>,
+) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    let zone = get_monitor_config_mut(&mut config, config_name)?.get_zone_mut(zone_name)?;
+    zone.x = x.unwrap_or(zone.x);
+    zone.y = y.unwrap_or(zone.y);
+    zone.width = width.unwrap_or(zone.width);
+    zone.height = height.unwrap_or(zone.height);
+    println!("{}", zone);
+    config::save_cfg_file(path, &config)
+}
+
+pub fn rename_zone_cmd(
+    path: &Path,
+    config_name: &str,
+    zone_name: &str,
+    new_name: &str,
+) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    get_monitor_config_mut(&mut config, config_name)?.rename_zone(zone_name, new_name)?;
+    for rule in &mut config.rules {
+        if rule.config == config_name && rule.zone == zone_name {
+            rule.zone = new_name.to_string();
+        }
+    }
+    config::save_cfg_file(path, &config)
+}
+
+pub fn move_zone_cmd(
+    path: &Path,
+    config_name: &str,
+    zone_name: &str,
+    dx: Length,
+    dy: Length,
+) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    let zone = get_monitor_config_mut(&mut config, config_name)?.get_zone_mut(zone_name)?;
+    let offset = |length: Length, offset: Length| -> Result<Length, ConfigError> {
+        let zone = zone_name.to_string();
+        length.offset(offset).map_err(|e| match e {
+            OffsetError::MixedUnits => ConfigError::MixedUnits {
+                zone,
+                length,
+                offset,
+            },
+            OffsetError::Overflow => ConfigError::OffsetOverflow {
+                zone,
+                length,
+                offset,
+            },
+        })
+    };
+    zone.x = offset(zone.x, dx)?;
+    zone.y = offset(zone.y, dy)?;
+    println!("{}", zone);
+    config::save_cfg_file(path, &config)
+}
+
+pub fn assign_cmd(
+    path: &Path,
+    monitor_name: &str,
+    config_name: &str,
+    desktop: Option<Desktop>,
+) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    get_monitor_config_mut(&mut config, config_name)?;
+    let monitor = get_monitor_mut(&mut config, monitor_name)?;
+    match desktop {
+        Some(desktop) => {
+            monitor.desktops.retain(|a| a.desktop != desktop);
+            monitor.desktops.push(config::DesktopAssignment {
+                desktop,
+                config: config_name.to_string(),
+            });
+        }
+        None => monitor.config = Some(config_name.to_string()),
+    }
+    config::save_cfg_file(path, &config)
+}
+
+pub fn unassing_cmd(path: &Path, monitor_name: &str, desktop: Option<&Desktop>) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    let monitor = get_monitor_mut(&mut config, monitor_name)?;
+    match desktop {
+        Some(desktop) => monitor.desktops.retain(|a| &a.desktop != desktop),
+        None => monitor.config = None,
+    }
+    config::save_cfg_file(path, &config)
+}
+
+pub fn restore_backup_cmd(path: &Path, backup: usize) -> CmdResult {
+    config::restore_backup(path, backup)?;
+    println!("Restored {} from backup {}", path.display(), backup);
+    Ok(())
 }
 
-pub fn refresh_global_pos_cmd() {
-    let mut config = config::load_cfg_file();
-    let (conn, screen) = make_conn();
-    config.refresh_all_global_monitor_pos(&conn, screen.root);
-    config::save_cfg_file(&config);
+pub fn refresh_global_pos_cmd(path: &Path) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    let (conn, screen) = make_conn()?;
+    config.refresh_all_global_monitor_pos(&*conn, screen.root)?;
+    config::save_cfg_file(path, &config)
 }
 
-pub fn refresh_sizes_cmd() {
-    let mut config = config::load_cfg_file();
-    let (conn, screen) = make_conn();
-    config.refresh_all_monitor_sizes(&conn, screen.root);
-    config::save_cfg_file(&config);
+pub fn refresh_sizes_cmd(path: &Path) -> CmdResult {
+    let mut config = config::load_cfg_file(path)?;
+    let (conn, screen) = make_conn()?;
+    config.refresh_all_monitor_sizes(&*conn, screen.root)?;
+    config::save_cfg_file(path, &config)
 }
@@ -1,13 +1,22 @@
-use clap::{Parser, Subcommand};
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::{ArgGroup, Parser, Subcommand};
 
 mod cmd_impl;
-mod example;
 
 use crate::cmd_impl::*;
+use lancy_zones::{
+    config::{self, Desktop, Length},
+    layout::{CellSpan, Template},
+    window::WindowType,
+};
 
 #[derive(Debug, Parser)]
 #[command(name = "lancy-zones-cfg")]
 struct Cli {
+    /// Path to the config file (overrides $LANCY_ZONES_CONFIG and the XDG default)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -16,6 +25,9 @@ struct Cli {
 enum Commands {
     #[command()]
     Info {},
+    /// Check the config for errors and warnings
+    #[command()]
+    Validate {},
     #[command()]
     Reinit {},
     #[command(arg_required_else_help = true)]
@@ -26,9 +38,17 @@ enum Commands {
     Assign {
         monitor_name: String,
         config_name: String,
+        /// Only use the config on this desktop, by index (starting at 0) or name
+        #[arg(long)]
+        desktop: Option<Desktop>,
     },
     #[command(arg_required_else_help = true)]
-    Unassign { monitor_name: String },
+    Unassign {
+        monitor_name: String,
+        /// Only remove the assignment for this desktop
+        #[arg(long)]
+        desktop: Option<Desktop>,
+    },
     #[command(arg_required_else_help = true)]
     AddZone {
         config_name: String,
@@ -38,34 +58,204 @@ enum Commands {
         width: i16,
         height: i16,
     },
-    // #[command(arg_required_else_help = true)]
-    // AddZoneRel {
-    //     config_name: String,
-    //     zone_name: String,
-    //     x: String,
-    //     y: String,
-    //     width: String,
-    //     height: String,
-    // },
+    /// Add a zone with pixel (40, 40px), percent (33%) or fraction (0.33) geometry
+    #[command(arg_required_else_help = true)]
+    AddZoneRel {
+        config_name: String,
+        zone_name: String,
+        x: Length,
+        y: Length,
+        width: Length,
+        height: Length,
+    },
+    /// Replace the zones of a config with a grid sized for its monitor
+    #[command(arg_required_else_help = true)]
+    Grid {
+        config_name: String,
+        /// Relative column widths, e.g. 1,2,1
+        #[arg(long, value_delimiter = ',', default_value = "1")]
+        cols: Vec<u32>,
+        /// Relative row heights, e.g. 1,1
+        #[arg(long, value_delimiter = ',', default_value = "1")]
+        rows: Vec<u32>,
+        /// Merge a block of cells into one zone, e.g. r1c1:r2c2 (repeatable)
+        #[arg(long = "merge")]
+        merges: Vec<CellSpan>,
+        /// Size the grid for this monitor instead of the one the config is assigned to
+        #[arg(long)]
+        monitor: Option<String>,
+    },
+    /// Replace the zones of a config with a built-in layout
+    #[command(arg_required_else_help = true)]
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommands,
+    },
+    /// Position the zones of a config relative to the work area left free by panels and docks
+    #[command(arg_required_else_help = true)]
+    WorkArea {
+        config_name: String,
+        #[arg(action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Monitor assignments for a specific set of connected outputs
+    #[command(arg_required_else_help = true)]
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommands,
+    },
+    /// Snap new windows into a zone by their class, title or type
+    #[command(arg_required_else_help = true)]
+    Rule {
+        #[command(subcommand)]
+        command: RuleCommands,
+    },
+    /// Zones windows were snapped into, restored when they are mapped again
+    #[command(arg_required_else_help = true)]
+    State {
+        #[command(subcommand)]
+        command: StateCommands,
+    },
     #[command(arg_required_else_help = true)]
     RemoveZone {
         config_name: String,
         zone_name: String,
     },
+    /// Change any of the position and size values of a zone
+    #[command(arg_required_else_help = true)]
+    EditZone {
+        config_name: String,
+        zone_name: String,
+        #[arg(long, allow_hyphen_values = true)]
+        x: Option<Length>,
+        #[arg(long, allow_hyphen_values = true)]
+        y: Option<Length>,
+        #[arg(long)]
+        width: Option<Length>,
+        #[arg(long)]
+        height: Option<Length>,
+    },
+    #[command(arg_required_else_help = true)]
+    RenameZone {
+        config_name: String,
+        zone_name: String,
+        new_name: String,
+    },
+    /// Move a zone by an offset in the same unit as its position
+    #[command(arg_required_else_help = true)]
+    MoveZone {
+        config_name: String,
+        zone_name: String,
+        #[arg(long, allow_hyphen_values = true, default_value = "0")]
+        dx: Length,
+        #[arg(long, allow_hyphen_values = true, default_value = "0")]
+        dy: Length,
+    },
+    /// Roll the config back to one of its backups (1 is the most recent)
+    #[command()]
+    RestoreBackup {
+        #[arg(
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u64).range(1..=config::BACKUP_COUNT as u64)
+        )]
+        backup: u64,
+    },
     #[command()]
     RefrashGlobalPos {},
     #[command()]
     RefrshSizes {},
 }
 
-fn main() {
+#[derive(Debug, Subcommand)]
+enum TemplateCommands {
+    /// List the available templates
+    #[command()]
+    List {},
+    /// Expand a template into the zones of a config, sized for its monitor
+    #[command(arg_required_else_help = true)]
+    Apply {
+        config_name: String,
+        template: Template,
+        /// Number of columns, rows, cells or zones, depending on the template
+        #[arg(long)]
+        count: Option<usize>,
+        /// Size the zones for this monitor instead of the one the config is assigned to
+        #[arg(long)]
+        monitor: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ProfileCommands {
+    /// Save the current assignments of the connected monitors as a profile
+    #[command(arg_required_else_help = true)]
+    Save { profile_name: String },
+    /// List the profiles and the outputs they are used for
+    #[command()]
+    List {},
+}
+
+#[derive(Debug, Subcommand)]
+enum RuleCommands {
+    /// Add a rule, replacing one of the same name. At least one matcher is required
+    #[command(
+        arg_required_else_help = true,
+        group(
+            ArgGroup::new("matcher")
+                .args(["instance", "class", "title", "window_type"])
+                .required(true)
+                .multiple(true)
+        )
+    )]
+    Add {
+        rule_name: String,
+        config_name: String,
+        zone_name: String,
+        /// Instance part of WM_CLASS
+        #[arg(long)]
+        instance: Option<String>,
+        /// Class part of WM_CLASS
+        #[arg(long)]
+        class: Option<String>,
+        /// Regex for the window title
+        #[arg(long)]
+        title: Option<String>,
+        /// Window type, e.g. normal or dialog
+        #[arg(long = "type")]
+        window_type: Option<WindowType>,
+    },
+    /// List the rules in the order they are tried
+    #[command()]
+    List {},
+    /// Remove a rule
+    #[command(arg_required_else_help = true)]
+    Remove { rule_name: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum StateCommands {
+    /// Forget all remembered window placements
+    #[command()]
+    Clear {},
+}
+
+fn main() -> ExitCode {
     let args = Cli::parse();
 
-    match args.command {
-        Commands::Info {} => list_cmd(),
-        Commands::Reinit {} => reinit_cmd(),
-        Commands::CreateConfig { config_name } => create_config_cmd(&config_name),
-        Commands::RemoveConfig { config_name } => remove_config_cmd(&config_name),
+    let Some(path) = config::resolve_config_path(args.config.as_deref()) else {
+        eprintln!(
+            "Could not determine config path. Set $HOME, $XDG_CONFIG_HOME, ${} or pass --config.",
+            config::CONFIG_PATH_ENV
+        );
+        return ExitCode::FAILURE;
+    };
+
+    let result = match args.command {
+        Commands::Info {} => list_cmd(&path),
+        Commands::Validate {} => validate_cmd(&path),
+        Commands::Reinit {} => reinit_cmd(&path),
+        Commands::CreateConfig { config_name } => create_config_cmd(&path, &config_name),
+        Commands::RemoveConfig { config_name } => remove_config_cmd(&path, &config_name),
         Commands::AddZone {
             config_name,
             zone_name,
@@ -73,14 +263,123 @@ fn main() {
             y,
             width,
             height,
-        } => add_zone_cmd(&config_name, &zone_name, x, y, width, height),
-        Commands::RemoveZone { .. } => todo!(),
+        } => add_zone_cmd(
+            &path,
+            &config_name,
+            &zone_name,
+            x.into(),
+            y.into(),
+            width.into(),
+            height.into(),
+        ),
+        Commands::AddZoneRel {
+            config_name,
+            zone_name,
+            x,
+            y,
+            width,
+            height,
+        } => add_zone_cmd(&path, &config_name, &zone_name, x, y, width, height),
+        Commands::Grid {
+            config_name,
+            cols,
+            rows,
+            merges,
+            monitor,
+        } => grid_cmd(
+            &path,
+            &config_name,
+            &cols,
+            &rows,
+            &merges,
+            monitor.as_deref(),
+        ),
+        Commands::Template { command } => match command {
+            TemplateCommands::List {} => template_list_cmd(),
+            TemplateCommands::Apply {
+                config_name,
+                template,
+                count,
+                monitor,
+            } => template_apply_cmd(&path, &config_name, template, count, monitor.as_deref()),
+        },
+        Commands::WorkArea {
+            config_name,
+            enabled,
+        } => work_area_cmd(&path, &config_name, enabled),
+        Commands::Profile { command } => match command {
+            ProfileCommands::Save { profile_name } => profile_save_cmd(&path, &profile_name),
+            ProfileCommands::List {} => profile_list_cmd(&path),
+        },
+        Commands::Rule { command } => match command {
+            RuleCommands::Add {
+                rule_name,
+                config_name,
+                zone_name,
+                instance,
+                class,
+                title,
+                window_type,
+            } => rule_add_cmd(
+                &path,
+                config::Rule {
+                    name: rule_name,
+                    instance,
+                    class,
+                    title,
+                    window_type,
+                    config: config_name,
+                    zone: zone_name,
+                },
+            ),
+            RuleCommands::List {} => rule_list_cmd(&path),
+            RuleCommands::Remove { rule_name } => rule_remove_cmd(&path, &rule_name),
+        },
+        Commands::State { command } => match command {
+            StateCommands::Clear {} => state_clear_cmd(),
+        },
+        Commands::RemoveZone {
+            config_name,
+            zone_name,
+        } => remove_zone_cmd(&path, &config_name, &zone_name),
+        Commands::EditZone {
+            config_name,
+            zone_name,
+            x,
+            y,
+            width,
+            height,
+        } => edit_zone_cmd(&path, &config_name, &zone_name, x, y, width, height),
+        Commands::RenameZone {
+            config_name,
+            zone_name,
+            new_name,
+        } => rename_zone_cmd(&path, &config_name, &zone_name, &new_name),
+        Commands::MoveZone {
+            config_name,
+            zone_name,
+            dx,
+            dy,
+        } => move_zone_cmd(&path, &config_name, &zone_name, dx, dy),
         Commands::Assign {
             monitor_name,
             config_name,
-        } => assign_cmd(&monitor_name, &config_name),
-        Commands::Unassign { monitor_name } => unassing_cmd(&monitor_name),
-        Commands::RefrashGlobalPos {} => refresh_global_pos_cmd(),
-        Commands::RefrshSizes {} => refresh_sizes_cmd(),
+            desktop,
+        } => assign_cmd(&path, &monitor_name, &config_name, desktop),
+        Commands::Unassign {
+            monitor_name,
+            desktop,
+        } => unassing_cmd(&path, &monitor_name, desktop.as_ref()),
+        Commands::RestoreBackup { backup } => restore_backup_cmd(&path, backup as usize),
+        Commands::RefrashGlobalPos {} => refresh_global_pos_cmd(&path),
+        Commands::RefrshSizes {} => refresh_sizes_cmd(&path),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
     }
 }
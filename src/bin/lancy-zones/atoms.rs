@@ -11,13 +11,29 @@ pub struct AtomContainer {
     pub wm_type_notification: u32,
     pub net_extents: u32,
     pub gtk_extents: u32,
+    /// `_NET_WM_CM_S<n>`, owned by the compositing manager of our screen.
+    pub net_wm_cm: u32,
+    /// Client message type the config watcher uses to request a reload.
+    pub lancy_zones_reload: u32,
+    pub net_workarea: u32,
+    pub net_current_desktop: u32,
+    pub net_desktop_names: u32,
+    pub net_wm_strut: u32,
+    pub net_wm_strut_partial: u32,
+    /// Property on snapped windows with their original size and zone geometry.
+    pub lancy_zones_geometry: u32,
+    /// Property on snapped windows naming the monitor, layout and zone they are in.
+    pub lancy_zones_zone: u32,
     pub no_decorations_hint: [u32; 5],
 }
 
 impl AtomContainer {
     pub const NO_DECORATIONS_HINT: [u32; 5] = [2, 0, 0, 0, 0];
 
-    pub fn new<'a, C: Connection + 'a>(conn: &'a C) -> Result<Self, ReplyOrIdError> {
+    pub fn new<'a, C: Connection + 'a>(
+        conn: &'a C,
+        screen_num: usize,
+    ) -> Result<Self, ReplyOrIdError> {
         let wm_protocols = conn.intern_atom(false, b"WM_PROTOCOLS")?.reply()?.atom;
         let wm_delete_window = conn.intern_atom(false, b"WM_DELETE_WINDOW")?.reply()?.atom;
         let net_wm_state = conn.intern_atom(false, b"_NET_WM_STATE")?.reply()?.atom;
@@ -43,6 +59,33 @@ impl AtomContainer {
             .intern_atom(false, b"_GTK_FRAME_EXTENTS")?
             .reply()?
             .atom;
+        let net_wm_cm = conn
+            .intern_atom(false, format!("_NET_WM_CM_S{}", screen_num).as_bytes())?
+            .reply()?
+            .atom;
+        let lancy_zones_reload = conn
+            .intern_atom(false, b"_LANCY_ZONES_RELOAD")?
+            .reply()?
+            .atom;
+        let net_workarea = conn.intern_atom(false, b"_NET_WORKAREA")?.reply()?.atom;
+        let net_current_desktop = conn
+            .intern_atom(false, b"_NET_CURRENT_DESKTOP")?
+            .reply()?
+            .atom;
+        let net_desktop_names = conn
+            .intern_atom(false, b"_NET_DESKTOP_NAMES")?
+            .reply()?
+            .atom;
+        let net_wm_strut = conn.intern_atom(false, b"_NET_WM_STRUT")?.reply()?.atom;
+        let net_wm_strut_partial = conn
+            .intern_atom(false, b"_NET_WM_STRUT_PARTIAL")?
+            .reply()?
+            .atom;
+        let lancy_zones_geometry = conn
+            .intern_atom(false, b"_LANCY_ZONES_GEOMETRY")?
+            .reply()?
+            .atom;
+        let lancy_zones_zone = conn.intern_atom(false, b"_LANCY_ZONES_ZONE")?.reply()?.atom;
 
         Ok(Self {
             wm_protocols,
@@ -56,6 +99,15 @@ impl AtomContainer {
             wm_type_notification,
             net_extents,
             gtk_extents,
+            net_wm_cm,
+            lancy_zones_reload,
+            net_workarea,
+            net_current_desktop,
+            net_desktop_names,
+            net_wm_strut,
+            net_wm_strut_partial,
+            lancy_zones_geometry,
+            lancy_zones_zone,
         })
     }
 }
@@ -1,34 +1,199 @@
 use std::rc::Rc;
 
 use x11rb::{
-    connection::Connection, errors::ReplyOrIdError, protocol::xproto::*,
+    connection::Connection,
+    errors::ReplyOrIdError,
+    protocol::{
+        render::{self, ConnectionExt as _, PictOp, Pictformat, PictureWrapper},
+        xproto::*,
+    },
 };
 
+use lancy_zones::config::{Color, Theme};
+
+/// The parts of a zone that get their own colour from the [`Theme`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Part {
+    ZoneFill,
+    ZoneBorder,
+    ActiveFill,
+    ActiveBorder,
+    Background,
+    Label,
+    ActiveLabel,
+}
+
+impl Part {
+    const ALL: [Part; 7] = [
+        Part::ZoneFill,
+        Part::ZoneBorder,
+        Part::ActiveFill,
+        Part::ActiveBorder,
+        Part::Background,
+        Part::Label,
+        Part::ActiveLabel,
+    ];
+
+    fn color(self, theme: &Theme) -> Color {
+        match self {
+            Part::ZoneFill => theme.zone_fill,
+            Part::ZoneBorder => theme.zone_border,
+            Part::ActiveFill => theme.active_fill,
+            Part::ActiveBorder => theme.active_border,
+            Part::Background => theme.background,
+            Part::Label => theme.label,
+            Part::ActiveLabel => theme.active_label,
+        }
+    }
+
+    /// Fills get the configured alpha, borders and labels stay opaque.
+    fn is_translucent(self) -> bool {
+        matches!(self, Part::ZoneFill | Part::ActiveFill | Part::Background)
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+enum Backend<C: Connection> {
+    /// One GC per part and theme, the whole window gets one opacity via the WM hint.
+    Core(Vec<Vec<GcontextWrapper<Rc<C>>>>),
+    /// XRender fills on a 32 bit ARGB window with per part alpha.
+    Render {
+        picture: PictureWrapper<Rc<C>>,
+        palettes: Vec<Vec<render::Color>>,
+    },
+}
+
 pub struct Colors<C: Connection> {
-    pub white: GcontextWrapper<Rc<C>>,
-    pub black: GcontextWrapper<Rc<C>>,
+    conn: Rc<C>,
+    backend: Backend<C>,
+    line_thickness: Vec<u16>,
+    /// Pixel value the overlay window should use as background.
+    pub background_pixel: u32,
 }
 
 impl<C: Connection> Colors<C> {
-    pub fn new(conn: Rc<C>, win_id: Window, screen: &Screen) -> Result<Self, ReplyOrIdError> {
-        let white_gcw = GcontextWrapper::create_gc(
-            conn.clone(),
-            win_id,
-            &CreateGCAux::new()
-                .graphics_exposures(0)
-                .foreground(screen.white_pixel),
-        )?;
-        let black_gcw = GcontextWrapper::create_gc(
+    /// Allocates the theme colours from the default colormap and creates core GCs for them.
+    pub fn new(
+        conn: Rc<C>,
+        win_id: Window,
+        screen: &Screen,
+        themes: &[Theme],
+    ) -> Result<Self, ReplyOrIdError> {
+        let mut palettes = Vec::with_capacity(themes.len());
+        for theme in themes {
+            let mut gcs = Vec::with_capacity(Part::ALL.len());
+            for part in Part::ALL {
+                let pixel = alloc_pixel(&*conn, screen, part.color(theme))?;
+                gcs.push(GcontextWrapper::create_gc(
+                    conn.clone(),
+                    win_id,
+                    &CreateGCAux::new().graphics_exposures(0).foreground(pixel),
+                )?);
+            }
+            palettes.push(gcs);
+        }
+
+        let background_pixel = match themes.first() {
+            Some(theme) => alloc_pixel(&*conn, screen, theme.background)?,
+            None => screen.black_pixel,
+        };
+
+        Ok(Colors {
+            conn,
+            backend: Backend::Core(palettes),
+            line_thickness: themes.iter().map(|t| t.line_thickness).collect(),
+            background_pixel,
+        })
+    }
+
+    /// Renders through a picture on the ARGB window `win_id` with format `format`.
+    pub fn new_argb(
+        conn: Rc<C>,
+        win_id: Window,
+        format: Pictformat,
+        themes: &[Theme],
+        alpha: f32,
+    ) -> Result<Self, ReplyOrIdError> {
+        let picture = PictureWrapper::create_picture(
             conn.clone(),
             win_id,
-            &CreateGCAux::new()
-                .graphics_exposures(0)
-                .foreground(screen.black_pixel),
+            format,
+            &render::CreatePictureAux::new(),
         )?;
+        let palettes = themes
+            .iter()
+            .map(|theme| {
+                Part::ALL
+                    .iter()
+                    .map(|part| {
+                        let part_alpha = if part.is_translucent() { alpha } else { 1.0 };
+                        premultiplied(part.color(theme), part_alpha)
+                    })
+                    .collect()
+            })
+            .collect();
 
         Ok(Colors {
-            white: white_gcw,
-            black: black_gcw,
+            conn,
+            backend: Backend::Render { picture, palettes },
+            line_thickness: themes.iter().map(|t| t.line_thickness).collect(),
+            // fully transparent in an ARGB visual
+            background_pixel: 0,
         })
     }
+
+    pub fn line_thickness(&self, theme: usize) -> u16 {
+        self.line_thickness[theme]
+    }
+
+    pub fn fill_rectangles(
+        &self,
+        win_id: Window,
+        theme: usize,
+        part: Part,
+        rects: &[Rectangle],
+    ) -> Result<(), ReplyOrIdError> {
+        match &self.backend {
+            Backend::Core(palettes) => {
+                let gc = palettes[theme][part.index()].gcontext();
+                self.conn.poly_fill_rectangle(win_id, gc, rects)?;
+            }
+            Backend::Render { picture, palettes } => {
+                self.conn.render_fill_rectangles(
+                    PictOp::SRC,
+                    picture.picture(),
+                    palettes[theme][part.index()],
+                    rects,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn alloc_pixel<C: Connection>(
+    conn: &C,
+    screen: &Screen,
+    color: Color,
+) -> Result<u32, ReplyOrIdError> {
+    let (r, g, b) = color.to_rgb16();
+    Ok(conn
+        .alloc_color(screen.default_colormap, r, g, b)?
+        .reply()?
+        .pixel)
+}
+
+fn premultiplied(color: Color, alpha: f32) -> render::Color {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let (r, g, b) = color.to_rgb16();
+    let scale = |c: u16| (c as f32 * alpha).round() as u16;
+    render::Color {
+        red: scale(r),
+        green: scale(g),
+        blue: scale(b),
+        alpha: scale(u16::MAX),
+    }
 }
@@ -0,0 +1,66 @@
+use x11rb::{connection::Connection, errors::ReplyOrIdError, protocol::xproto::*};
+
+use lancy_zones::config::Config;
+
+/// Modifier mask, modifier keycodes and drop button resolved from the config.
+pub struct ActivationKeys {
+    pub mask: KeyButMask,
+    pub keycodes: Vec<Keycode>,
+    pub drop_button: u32,
+}
+
+impl ActivationKeys {
+    pub fn new<C: Connection>(conn: &C, config: &Config) -> Result<Self, ReplyOrIdError> {
+        let mask = config
+            .activation_modifiers
+            .iter()
+            .fold(KeyButMask::from(0_u16), |mask, modifier| {
+                mask | modifier.mask()
+            });
+
+        let keysyms: Vec<Keysym> = config
+            .activation_modifiers
+            .iter()
+            .flat_map(|modifier| modifier.keysyms())
+            .collect();
+        let keycodes = keysyms_to_keycodes(conn, &keysyms)?;
+
+        Ok(ActivationKeys {
+            mask,
+            keycodes,
+            drop_button: config.drop_button as u32,
+        })
+    }
+
+    /// True if every activation modifier is set in `state`.
+    pub fn is_active(&self, state: KeyButMask) -> bool {
+        u16::from(state) & u16::from(self.mask) == u16::from(self.mask)
+    }
+
+    pub fn is_modifier_key(&self, keycode: u32) -> bool {
+        self.keycodes.iter().any(|k| *k as u32 == keycode)
+    }
+}
+
+/// Looks up every keycode that produces one of `keysyms` in the current keyboard mapping.
+fn keysyms_to_keycodes<C: Connection>(
+    conn: &C,
+    keysyms: &[Keysym],
+) -> Result<Vec<Keycode>, ReplyOrIdError> {
+    let setup = conn.setup();
+    let min_keycode = setup.min_keycode;
+    let count = setup.max_keycode - min_keycode + 1;
+    let mapping = conn.get_keyboard_mapping(min_keycode, count)?.reply()?;
+
+    let per_keycode = mapping.keysyms_per_keycode as usize;
+    if per_keycode == 0 {
+        return Ok(Vec::new());
+    }
+    Ok(mapping
+        .keysyms
+        .chunks(per_keycode)
+        .enumerate()
+        .filter(|(_, syms)| syms.iter().any(|sym| keysyms.contains(sym)))
+        .map(|(i, _)| min_keycode + i as u8)
+        .collect())
+}
@@ -1,32 +1,113 @@
 mod atoms;
 mod colors;
+mod font;
+mod input;
 mod overlay;
-use std::path::Path;
+mod snapped;
+mod watcher;
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::rc::Rc;
 
-use lancy_zones::config::{init_cfg_file, load_cfg_file};
+use clap::Parser;
+use lancy_zones::config::{self, ConfigError, init_cfg_file, load_cfg_file};
+use lancy_zones::state;
 use x11rb::connection::Connection;
 
 use crate::atoms::AtomContainer;
 use crate::overlay::Overlay;
 
-fn main() {
-    let (conn, screen_num) = x11rb::connect(None).unwrap();
+#[derive(Debug, Parser)]
+#[command(name = "lancy-zones")]
+struct Cli {
+    /// Path to the config file (overrides $LANCY_ZONES_CONFIG and the XDG default)
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = Cli::parse();
+
+    let Some(path) = config::resolve_config_path(args.config.as_deref()) else {
+        eprintln!(
+            "Could not determine config path. Set $HOME, $XDG_CONFIG_HOME, ${} or pass --config.",
+            config::CONFIG_PATH_ENV
+        );
+        return ExitCode::FAILURE;
+    };
+
+    let (conn, screen_num) = match x11rb::connect(None) {
+        Ok(connection) => connection,
+        Err(e) => {
+            eprintln!("Error: {}", ConfigError::from(e));
+            return ExitCode::FAILURE;
+        }
+    };
     let conn = Rc::new(conn);
     let screen = conn.setup().roots[screen_num].clone();
 
-    let path = Path::new("~/.config/lancy-zones/config.json");
-    if !path.exists() {
-        init_cfg_file(&conn, screen.root);
+    let config = if path.exists() {
+        load_cfg_file(&path)
+    } else {
+        init_cfg_file(&conn, screen.root, &path)
+    };
+    let mut config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(e) = config.refresh_all_global_monitor_pos(&conn, screen.root) {
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    let diagnostics = config.validate();
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+    if diagnostics.iter().any(|d| d.is_error()) {
+        eprintln!("Refusing to start with an invalid config, see lancy-zones-cfg validate");
+        return ExitCode::FAILURE;
     }
-    let mut config = load_cfg_file();
-    config.refresh_all_global_monitor_pos(&conn, screen.root);
     let config = Rc::new(config);
 
-    let atoms = Rc::new(AtomContainer::new(&conn).unwrap());
+    let atoms = match AtomContainer::new(&conn, screen_num) {
+        Ok(atoms) => Rc::new(atoms),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let reload = atoms.lancy_zones_reload;
     let screen = Rc::new(screen);
-    let mut overlay = Overlay::new(conn, screen.clone(), atoms, config.clone())
-        .init()
-        .unwrap();
-    _ = overlay.listen();
+    let state_path = state::get_state_path();
+    if state_path.is_none() {
+        eprintln!("Could not determine state path, window placements are not kept.");
+    }
+    let overlay = Overlay::new(
+        conn,
+        screen.clone(),
+        atoms,
+        config.clone(),
+        path.clone(),
+        state_path,
+    )
+    .init();
+    let mut overlay = match overlay {
+        Ok(overlay) => overlay,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(e) = watcher::spawn(&path, overlay.win_id(), reload) {
+        eprintln!("Not watching {} for changes: {}", path.display(), e);
+    }
+    if let Err(e) = overlay.listen() {
+        eprintln!("Error: {}", e);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
 }
@@ -1,4 +1,9 @@
-use std::{cmp::Ordering, rc::Rc};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    rc::Rc,
+};
 
 use x11rb::{
     COPY_DEPTH_FROM_PARENT,
@@ -6,6 +11,8 @@ use x11rb::{
     errors::ReplyOrIdError,
     protocol::{
         Event,
+        randr::{self, ConnectionExt as _},
+        render::{self, ConnectionExt as _},
         shape::{self, ConnectionExt as _},
         xinput::{ConnectionExt as _, Device, XIEventMask},
         xproto::{PixmapWrapper, *},
@@ -14,20 +21,71 @@ use x11rb::{
     wrapper::ConnectionExt as _,
 };
 
-use crate::{atoms::AtomContainer, colors::Colors};
+use crate::{
+    atoms::AtomContainer,
+    colors::{Colors, Part},
+    font,
+    input::ActivationKeys,
+    snapped::{Change, SnappedWindow},
+};
 
-use lancy_zones::config::{Config, Zone};
+use lancy_zones::{
+    config::{self, Config, Monitor, ResolvedZone, Rule, RuleSet, Theme},
+    state::{self, State, ZoneRef},
+    util,
+    window::WindowType,
+    workarea::Area,
+};
+
+/// A zone in root window coordinates together with the index of its theme.
+struct ThemedZone {
+    zone: ResolvedZone,
+    /// Index of the monitor the zone is on.
+    monitor: usize,
+    /// Index of the monitor config the zone is in, `None` for the monitor background.
+    layout: Option<usize>,
+    theme: usize,
+    label: Option<String>,
+}
 
 pub struct Overlay<C: Connection> {
     conn: Rc<C>,
     screen: Rc<Screen>,
-    zones: Vec<Zone>,
+    /// Connected monitors with the configs assigned to them.
+    monitors: Vec<Monitor>,
+    /// Work area of each monitor.
+    work_areas: Vec<Area>,
+    /// Index of the current desktop and the names of all desktops.
+    desktop: u32,
+    desktop_names: Vec<String>,
+    zones: Vec<ThemedZone>,
+    themes: Vec<Theme>,
     atoms: Rc<AtomContainer>,
     colors: Option<Colors<C>>,
+    activation: Option<ActivationKeys>,
     config: Rc<Config>,
+    /// Name of the profile in use, if one matches the connected monitors.
+    profile: Option<String>,
+    /// Rules of `config`, compiled when it is loaded.
+    rules: RuleSet,
+    config_path: PathBuf,
+    /// Visual and picture format of the overlay window when it uses an ARGB visual.
+    argb: Option<(Visualid, render::Pictformat)>,
     win_id: Window,
     active_zone: Option<usize>,
     pixmap: Option<PixmapWrapper<Rc<C>>>,
+    /// Windows snapped into a zone, with the zone to follow when the layout changes and the
+    /// size to restore when they leave it.
+    snapped: HashMap<Window, SnappedWindow>,
+    /// Top level docks and windows with a strut, mapping or unmapping them changes the work
+    /// area.
+    struts: HashSet<Window>,
+    /// Top level windows that were mapped before, rules and remembered placements only
+    /// apply the first time.
+    seen: HashSet<Window>,
+    /// File with the zones windows were snapped into, read on every use so that clearing
+    /// it takes effect right away.
+    state_path: Option<PathBuf>,
 }
 
 impl<C: Connection> Overlay<C> {
@@ -36,6 +94,8 @@ impl<C: Connection> Overlay<C> {
         screen: Rc<Screen>,
         atoms: Rc<AtomContainer>,
         config: Rc<Config>,
+        config_path: PathBuf,
+        state_path: Option<PathBuf>,
     ) -> Self {
         assert!(
             conn.extension_information(shape::X11_EXTENSION_NAME)
@@ -50,80 +110,62 @@ impl<C: Connection> Overlay<C> {
             "XInput extension is required."
         );
 
-        let mut zones = Vec::new();
-        for monitor in &config.monitors {
-            // add background zone for correct rendering
-            zones.push(Zone {
-                name: "".to_string(),
-                x: monitor.x,
-                y: monitor.y,
-                width: monitor.width as i16,
-                height: monitor.height as i16,
-            });
-            if let Some(config) = &monitor.config {
-                for zone in &config.zones {
-                    let trans_zone = Zone {
-                        name: zone.name.clone(),
-                        x: zone.x + monitor.x,
-                        y: zone.y + monitor.y,
-                        width: zone.width,
-                        height: zone.height,
-                    };
-                    zones.push(trans_zone);
-                }
-            }
-        }
-
-        // Sort by biggest area first (ording::less). This helps rendering of zones that cover
-        // eachother, because the common case is a zone covered by farction of itself
-        zones.sort_by(|a, b| -> Ordering {
-            let a = a.get_area();
-            let b = b.get_area();
-            if a > b {
-                Ordering::Less
-            } else if a < b {
-                Ordering::Greater
-            } else {
-                Ordering::Equal
-            }
-        });
-
         let win_id = conn.generate_id().expect("Failed to generate window id.");
 
         Overlay {
             conn,
             screen,
-            zones,
+            monitors: Vec::new(),
+            work_areas: Vec::new(),
+            desktop: 0,
+            desktop_names: Vec::new(),
+            zones: Vec::new(),
+            themes: Vec::new(),
             atoms,
             colors: None,
+            activation: None,
+            profile: None,
+            rules: RuleSet::new(&config.rules),
             config,
+            config_path,
+            argb: None,
             win_id,
             active_zone: None,
             pixmap: None,
+            snapped: HashMap::new(),
+            struts: HashSet::new(),
+            seen: HashSet::new(),
+            state_path,
         }
     }
 
     pub fn init(mut self) -> Result<Self, ReplyOrIdError> {
-        let win_aux = CreateWindowAux::new()
+        let argb = self.find_argb_format()?;
+        self.argb = argb;
+        let mut win_aux = CreateWindowAux::new()
             .event_mask(EventMask::EXPOSURE | EventMask::STRUCTURE_NOTIFY)
             .background_pixel(self.screen.white_pixel)
             .override_redirect(1);
-        let opacity: u32 = (self.config.alpha * u32::MAX as f32) as u32;
-        let wm_normal_hints = [
-            15,                                  // Flags: PMinSize | PMaxSize
-            self.screen.width_in_pixels as u32,  // min width
-            self.screen.height_in_pixels as u32, // min height
-            self.screen.width_in_pixels as u32,  // max width
-            self.screen.height_in_pixels as u32, // max height
-            0,
-            0,
-            0,
-            0,
-            0,
-        ];
-
+        let (depth, visual) = match argb {
+            Some((visual, _)) => {
+                // a window with a different visual than its parent needs its own colormap
+                let colormap = self.conn.generate_id()?;
+                self.conn.create_colormap(
+                    ColormapAlloc::NONE,
+                    colormap,
+                    self.screen.root,
+                    visual,
+                )?;
+                win_aux = win_aux
+                    .background_pixel(0)
+                    .border_pixel(0)
+                    .colormap(colormap);
+                (32, visual)
+            }
+            None => (COPY_DEPTH_FROM_PARENT, 0),
+        };
         self.conn.create_window(
-            COPY_DEPTH_FROM_PARENT,
+            depth,
             self.win_id,
             self.screen.root,
             0,
@@ -132,7 +174,7 @@ impl<C: Connection> Overlay<C> {
             self.screen.height_in_pixels,
             0,
             WindowClass::INPUT_OUTPUT,
-            0,
+            visual,
             &win_aux,
         )?;
 
@@ -161,22 +203,6 @@ impl<C: Connection> Overlay<C> {
             &self.atoms.no_decorations_hint,
         )?;
 
-        self.conn.change_property32(
-            PropMode::REPLACE,
-            self.win_id,
-            self.atoms.wm_window_opacity,
-            AtomEnum::CARDINAL,
-            &[opacity],
-        )?;
-
-        self.conn.change_property32(
-            PropMode::REPLACE,
-            self.win_id,
-            AtomEnum::WM_NORMAL_HINTS,
-            AtomEnum::CARDINAL,
-            &wm_normal_hints,
-        )?;
-
         self.conn.change_property32(
             PropMode::REPLACE,
             self.win_id,
@@ -193,6 +219,42 @@ impl<C: Connection> Overlay<C> {
             &[self.atoms.net_wm_state_above],
         )?;
 
+        self.apply_root_size()?;
+        self.rebuild_zones()?;
+        self.apply_config()?;
+        self.load_snapped()?;
+        self.conn.flush()?;
+
+        Ok(self)
+    }
+
+    pub fn win_id(&self) -> Window {
+        self.win_id
+    }
+
+    /// Size hints, input shape and pixmap that depend on the size of the root window.
+    fn apply_root_size(&mut self) -> Result<(), ReplyOrIdError> {
+        let wm_normal_hints = [
+            15,                                  // Flags: PMinSize | PMaxSize
+            self.screen.width_in_pixels as u32,  // min width
+            self.screen.height_in_pixels as u32, // min height
+            self.screen.width_in_pixels as u32,  // max width
+            self.screen.height_in_pixels as u32, // max height
+            0,
+            0,
+            0,
+            0,
+            0,
+        ];
+
+        self.conn.change_property32(
+            PropMode::REPLACE,
+            self.win_id,
+            AtomEnum::WM_NORMAL_HINTS,
+            AtomEnum::CARDINAL,
+            &wm_normal_hints,
+        )?;
+
         self.pixmap = Some(PixmapWrapper::create_pixmap(
             self.conn.clone(),
             self.screen.root_depth,
@@ -209,20 +271,402 @@ impl<C: Connection> Overlay<C> {
             0,
             self.pixmap.as_ref().unwrap().pixmap(),
         )?;
+        Ok(())
+    }
 
-        self.colors = Some(Colors::new(self.conn.clone(), self.win_id, &*self.screen)?);
+    /// Follows RandR changes: resizes the overlay to the new root size and rebuilds the
+    /// zones for the monitors that are connected now.
+    fn handle_screen_change(&mut self) -> Result<(), ReplyOrIdError> {
+        let geometry = self.conn.get_geometry(self.screen.root)?.reply()?;
+        if (geometry.width, geometry.height)
+            != (self.screen.width_in_pixels, self.screen.height_in_pixels)
+        {
+            let mut screen = (*self.screen).clone();
+            screen.width_in_pixels = geometry.width;
+            screen.height_in_pixels = geometry.height;
+            self.screen = Rc::new(screen);
+
+            self.conn.configure_window(
+                self.win_id,
+                &ConfigureWindowAux::new()
+                    .width(u32::from(geometry.width))
+                    .height(u32::from(geometry.height)),
+            )?;
+            self.apply_root_size()?;
+        }
 
+        self.rebuild_zones()?;
+        self.apply_config()?;
+        self.resnap_windows()?;
         self.conn.flush()?;
+        Ok(())
+    }
 
-        Ok(self)
+    /// Detects the monitors, their work areas and the current desktop and resolves the
+    /// zones for them.
+    fn rebuild_zones(&mut self) -> Result<(), ReplyOrIdError> {
+        self.monitors = detect_monitors(
+            &*self.conn,
+            self.screen.root,
+            &self.config,
+            &mut self.profile,
+        )?;
+        self.work_areas = util::get_work_areas(&*self.conn, self.screen.root, &self.monitors)?;
+        self.desktop = util::get_current_desktop(&*self.conn, self.screen.root)?;
+        self.desktop_names = util::get_desktop_names(&*self.conn, self.screen.root)?;
+        self.resolve_zones();
+        Ok(())
+    }
+
+    /// Picks the zones of the configs assigned for the current desktop.
+    fn resolve_zones(&mut self) {
+        (self.zones, self.themes) = build_zones(
+            &self.config,
+            &self.monitors,
+            &self.work_areas,
+            self.desktop,
+            self.desktop_name(),
+        );
+        self.active_zone = None;
+    }
+
+    fn desktop_name(&self) -> Option<&str> {
+        self.desktop_names
+            .get(self.desktop as usize)
+            .map(String::as_str)
+    }
+
+    /// Sets up everything derived from the config that is not baked into the window itself:
+    /// opacity, colours and activation keys.
+    fn apply_config(&mut self) -> Result<(), ReplyOrIdError> {
+        // with an ARGB visual the alpha is part of every pixel instead
+        if self.argb.is_none() {
+            let opacity: u32 = (self.config.alpha * u32::MAX as f32) as u32;
+            self.conn.change_property32(
+                PropMode::REPLACE,
+                self.win_id,
+                self.atoms.wm_window_opacity,
+                AtomEnum::CARDINAL,
+                &[opacity],
+            )?;
+        }
+
+        let colors = match self.argb {
+            Some((_, format)) => Colors::new_argb(
+                self.conn.clone(),
+                self.win_id,
+                format,
+                &self.themes,
+                self.config.alpha,
+            )?,
+            None => Colors::new(self.conn.clone(), self.win_id, &self.screen, &self.themes)?,
+        };
+        self.conn.change_window_attributes(
+            self.win_id,
+            &ChangeWindowAttributesAux::new().background_pixel(colors.background_pixel),
+        )?;
+        self.colors = Some(colors);
+        self.activation = Some(ActivationKeys::new(&*self.conn, &self.config)?);
+        Ok(())
+    }
+
+    /// Loads the config file again and swaps it in. A config that fails to load or validate
+    /// is reported and the current one is kept.
+    fn reload_config(&mut self) -> Result<(), ReplyOrIdError> {
+        let mut config = match config::load_cfg_file(&self.config_path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Keeping the previous config, reload failed: {}", e);
+                return Ok(());
+            }
+        };
+        if let Err(e) = config.refresh_all_global_monitor_pos(&*self.conn, self.screen.root) {
+            eprintln!("Keeping the previous config, reload failed: {}", e);
+            return Ok(());
+        }
+
+        let diagnostics = config.validate();
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        if diagnostics.iter().any(|d| d.is_error()) {
+            eprintln!("Keeping the previous config, the new one is invalid");
+            return Ok(());
+        }
+
+        self.rules = RuleSet::new(&config.rules);
+        self.config = Rc::new(config);
+        self.rebuild_zones()?;
+        self.apply_config()?;
+        self.resnap_windows()?;
+        self.conn.flush()?;
+        println!("Reloaded {}", self.config_path.display());
+        Ok(())
+    }
+
+    /// Picks up the windows snapped by a previous run from their zone and geometry
+    /// properties.
+    fn load_snapped(&mut self) -> Result<(), ReplyOrIdError> {
+        let tree = self.conn.query_tree(self.screen.root)?.reply()?;
+        self.seen.extend(&tree.children);
+        for win in tree.children {
+            let client = self.client(win);
+            let zone = self
+                .conn
+                .get_property(
+                    false,
+                    client,
+                    self.atoms.lancy_zones_zone,
+                    AtomEnum::STRING,
+                    0,
+                    u32::MAX,
+                )?
+                .reply();
+            let geometry = self
+                .conn
+                .get_property(
+                    false,
+                    client,
+                    self.atoms.lancy_zones_geometry,
+                    AtomEnum::CARDINAL,
+                    0,
+                    6,
+                )?
+                .reply();
+            // the window may be gone by now
+            let (Ok(zone), Ok(geometry)) = (zone, geometry) else {
+                continue;
+            };
+            let Some(zone) = ZoneRef::from_property(&zone.value) else {
+                continue;
+            };
+            let values: Vec<u32> = geometry.value32().into_iter().flatten().collect();
+            if let Some(snapped) = SnappedWindow::from_property(zone, &values) {
+                self.snapped.insert(win, snapped);
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the snapped windows to the current geometry of their zones after the layout or
+    /// the monitors changed. Windows whose zone or monitor is gone stay where they are.
+    fn resnap_windows(&mut self) -> Result<(), ReplyOrIdError> {
+        let mut moves = Vec::new();
+        for (win, snapped) in &self.snapped {
+            if let Some(rect) = self.resolve_zone_ref(&snapped.zone) {
+                let current = &snapped.geometry;
+                if (rect.x, rect.y, rect.width, rect.height)
+                    != (current.x, current.y, current.width, current.height)
+                {
+                    moves.push((*win, rect));
+                }
+            }
+        }
+
+        for (win, rect) in moves {
+            let Some(snapped) = self.snapped.get_mut(&win) else {
+                continue;
+            };
+            snapped.resnap(rect);
+            let snapped = snapped.clone();
+            self.configure_into(win, rect)?;
+            self.write_snapped(win, &snapped)?;
+        }
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    /// Follows the strut of the top level `win` if it is a dock or reserves space already.
+    /// Returns true if it reserves space.
+    fn watch_struts(&mut self, win: Window) -> Result<bool, ReplyOrIdError> {
+        let client = self.client(win);
+        // the window may be gone already
+        let Ok(has_strut) = util::has_strut(&*self.conn, client) else {
+            return Ok(false);
+        };
+        let is_dock = util::get_window_info(&*self.conn, win)
+            .is_ok_and(|info| -> bool { info.window_type == Some(WindowType::Dock) });
+        if !has_strut && !is_dock {
+            return Ok(false);
+        }
+        self.conn.change_window_attributes(
+            client,
+            &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+        self.struts.insert(win);
+        Ok(has_strut)
+    }
+
+    /// Reads the work areas again after a panel changed. Returns true if they changed, the
+    /// zones and the windows snapped into them then follow.
+    fn update_work_areas(&mut self) -> Result<bool, ReplyOrIdError> {
+        let work_areas = util::get_work_areas(&*self.conn, self.screen.root, &self.monitors)?;
+        if work_areas == self.work_areas {
+            return Ok(false);
+        }
+        self.work_areas = work_areas;
+        self.resolve_zones();
+        self.resnap_windows()?;
+        Ok(true)
+    }
+
+    /// The zone at `index` of the current zones, as windows snapped into it refer to it.
+    fn zone_ref(&self, index: usize) -> ZoneRef {
+        let ThemedZone {
+            zone,
+            monitor,
+            layout,
+            ..
+        } = &self.zones[index];
+        ZoneRef {
+            monitor: self.monitors[*monitor].profile_key(),
+            config: layout
+                .map(|i| self.config.monitor_configs[i].name.clone())
+                .unwrap_or_default(),
+            name: zone.name.clone(),
+        }
+    }
+
+    /// Geometry of the zone `zone` refers to, resolved in its own layout whichever one the
+    /// current desktop shows. `None` if its monitor is gone or the layout lost the zone.
+    fn resolve_zone_ref(&self, zone: &ZoneRef) -> Option<Rectangle> {
+        let index = self
+            .monitors
+            .iter()
+            .position(|monitor| -> bool { monitor.is_known_as(&zone.monitor) })?;
+        let monitor = &self.monitors[index];
+        // windows dropped on the monitor background cover the whole monitor
+        if zone.name.is_empty() {
+            let area = monitor.area();
+            return Some(Rectangle {
+                x: area.x,
+                y: area.y,
+                width: area.width,
+                height: area.height,
+            });
+        }
+        let config_name = if zone.config.is_empty() {
+            monitor.config_for_desktop(self.desktop, self.desktop_name())?
+        } else {
+            &zone.config
+        };
+        let mc = self.config.get_monitor_config(config_name)?;
+        let area = if mc.work_area {
+            self.work_areas[index]
+        } else {
+            monitor.area()
+        };
+        let resolved = mc
+            .get_zone(&zone.name)?
+            .resolve(area.width, area.height)
+            .translate(area.x, area.y);
+        Some(zone_rect(&resolved))
+    }
+
+    /// The zone a rule snaps into, on the first monitor that currently shows the rule's
+    /// config.
+    fn find_rule_zone(&self, rule: &Rule) -> Option<ZoneRef> {
+        let monitor = self.monitors.iter().find(|monitor| -> bool {
+            monitor.config_for_desktop(self.desktop, self.desktop_name()) == Some(&rule.config)
+        })?;
+        Some(ZoneRef {
+            monitor: monitor.profile_key(),
+            config: rule.config.clone(),
+            name: rule.zone.clone(),
+        })
+    }
+
+    /// Moves a window that is mapped for the first time into the zone of the first matching
+    /// rule or, without one, the zone it was last snapped into.
+    fn place_mapped(&mut self, win: Window) -> Result<(), ReplyOrIdError> {
+        // windows that come back from being minimized stay where they are
+        if !self.seen.insert(win) {
+            return Ok(());
+        }
+        // the window may be gone again already
+        let Ok(info) = util::get_window_info(&*self.conn, win) else {
+            return Ok(());
+        };
+        let zone = match self.rules.matching(&info) {
+            Some(rule) => self.find_rule_zone(rule),
+            None => self.load_state().find(&info).cloned(),
+        };
+        let Some(zone) = zone else {
+            return Ok(());
+        };
+        let Some(rect) = self.resolve_zone_ref(&zone) else {
+            return Ok(());
+        };
+        if self.register(win, zone, rect)? {
+            self.configure_into(win, rect)?;
+            self.conn.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Visual and picture format for a 32 bit ARGB overlay. Only used when a compositing
+    /// manager is running, without one the alpha channel would be ignored.
+    fn find_argb_format(&self) -> Result<Option<(Visualid, render::Pictformat)>, ReplyOrIdError> {
+        if self
+            .conn
+            .extension_information(render::X11_EXTENSION_NAME)?
+            .is_none()
+        {
+            return Ok(None);
+        }
+        let owner = self
+            .conn
+            .get_selection_owner(self.atoms.net_wm_cm)?
+            .reply()?
+            .owner;
+        if owner == x11rb::NONE {
+            return Ok(None);
+        }
+
+        let Some(visual) = self
+            .screen
+            .allowed_depths
+            .iter()
+            .filter(|depth| depth.depth == 32)
+            .flat_map(|depth| &depth.visuals)
+            .find(|visual| visual.class == VisualClass::TRUE_COLOR)
+            .map(|visual| visual.visual_id)
+        else {
+            return Ok(None);
+        };
+
+        let formats = self.conn.render_query_pict_formats()?.reply()?;
+        let format = formats
+            .screens
+            .iter()
+            .flat_map(|screen| &screen.depths)
+            .flat_map(|depth| &depth.visuals)
+            .find(|pict_visual| pict_visual.visual == visual)
+            .map(|pict_visual| pict_visual.format);
+        Ok(format.map(|format| (visual, format)))
     }
 
     pub fn listen(&mut self) -> Result<(), ReplyOrIdError> {
         self.conn.change_window_attributes(
             self.screen.root,
-            &ChangeWindowAttributesAux::new()
-                .event_mask(EventMask::SUBSTRUCTURE_NOTIFY | EventMask::STRUCTURE_NOTIFY),
+            &ChangeWindowAttributesAux::new().event_mask(
+                EventMask::SUBSTRUCTURE_NOTIFY
+                    | EventMask::STRUCTURE_NOTIFY
+                    | EventMask::PROPERTY_CHANGE,
+            ),
         )?;
+        if self
+            .conn
+            .extension_information(randr::X11_EXTENSION_NAME)?
+            .is_some()
+        {
+            self.conn.randr_select_input(
+                self.screen.root,
+                randr::NotifyMask::SCREEN_CHANGE
+                    | randr::NotifyMask::CRTC_CHANGE
+                    | randr::NotifyMask::OUTPUT_CHANGE,
+            )?;
+        }
         self.conn.xinput_xi_select_events(
             self.screen.root,
             &[xinput::EventMask {
@@ -230,55 +674,127 @@ impl<C: Connection> Overlay<C> {
                 mask: vec![XIEventMask::RAW_KEY_RELEASE | XIEventMask::RAW_BUTTON_RELEASE],
             }],
         )?;
+        let tree = self.conn.query_tree(self.screen.root)?.reply()?;
+        for child in tree.children {
+            self.watch_struts(child)?;
+        }
         self.conn.flush()?;
 
         let mut is_showing = false;
         let mut win: Option<u32> = None;
         loop {
             let event = self.conn.wait_for_event()?;
-            let ctrl = self.button_pressed(KeyButMask::CONTROL).unwrap_or(false);
+            let activated = self.modifiers_pressed().unwrap_or(false);
             match event {
-                Event::ConfigureNotify(e) => {
-                    if ctrl {
-                        if !is_showing {
-                            is_showing = true;
-                            self.show()?;
-                        }
-                        win = Some(e.window);
-                        let pointer = self.conn.query_pointer(self.win_id)?.reply()?;
-                        self.find_active_zone(pointer.root_x, pointer.root_y);
+                // the configure of the snap itself, the modifiers may still be held
+                Event::ConfigureNotify(e)
+                    if self
+                        .snapped
+                        .get(&e.window)
+                        .is_some_and(|snapped| -> bool { !snapped.settled }) =>
+                {
+                    self.follow_snapped(&e)?;
+                }
+                Event::ConfigureNotify(e) if activated => {
+                    if !is_showing {
+                        is_showing = true;
+                        self.show()?;
+                    }
+                    win = Some(e.window);
+                    let pointer = self.conn.query_pointer(self.win_id)?.reply()?;
+                    self.find_active_zone(pointer.root_x, pointer.root_y);
+                }
+                Event::ConfigureNotify(e) if self.snapped.contains_key(&e.window) => {
+                    self.follow_snapped(&e)?;
+                }
+                Event::MapNotify(e) if e.event == self.screen.root && !e.override_redirect => {
+                    if self.watch_struts(e.window)? && self.update_work_areas()? && is_showing {
+                        self.draw_zones(self.win_id)?;
                     }
+                    self.place_mapped(e.window)?;
                 }
-                Event::XinputRawKeyRelease(e) => {
-                    if e.detail == 37 && is_showing {
+                Event::UnmapNotify(e) if self.struts.contains(&e.window) => {
+                    let changed = self.update_work_areas()?;
+                    if changed && is_showing {
+                        self.draw_zones(self.win_id)?;
+                    }
+                }
+                Event::DestroyNotify(e) => {
+                    self.snapped.remove(&e.window);
+                    self.seen.remove(&e.window);
+                    if self.struts.remove(&e.window) && self.update_work_areas()? && is_showing {
+                        self.draw_zones(self.win_id)?;
+                    }
+                }
+                Event::ClientMessage(e) if e.type_ == self.atoms.lancy_zones_reload => {
+                    if is_showing {
                         is_showing = false;
                         self.hide()?;
                     }
+                    self.reload_config()?;
                 }
-                Event::XinputRawButtonRelease(e) => {
-                    if e.detail == 1 {
-                        if is_showing {
-                            if ctrl {
-                                if let Some(active_win) = win {
-                                    self.snap_to_zone(active_win)?;
-                                    win = None;
-                                } else {
-                                    println!("Got no win outer");
-                                }
-                            }
-                            is_showing = false;
-                            self.hide()?;
+                Event::RandrScreenChangeNotify(_) | Event::RandrNotify(_) => {
+                    if is_showing {
+                        is_showing = false;
+                        self.hide()?;
+                    }
+                    self.handle_screen_change()?;
+                }
+                // window managers update the work area whenever a strut changes
+                Event::PropertyNotify(e)
+                    if (e.window == self.screen.root && e.atom == self.atoms.net_workarea)
+                        || e.atom == self.atoms.net_wm_strut
+                        || e.atom == self.atoms.net_wm_strut_partial =>
+                {
+                    let changed = self.update_work_areas()?;
+                    if changed && is_showing {
+                        self.draw_zones(self.win_id)?;
+                    }
+                }
+                Event::PropertyNotify(e)
+                    if e.window == self.screen.root
+                        && (e.atom == self.atoms.net_current_desktop
+                            || e.atom == self.atoms.net_desktop_names) =>
+                {
+                    self.desktop = util::get_current_desktop(&*self.conn, self.screen.root)?;
+                    self.desktop_names = util::get_desktop_names(&*self.conn, self.screen.root)?;
+                    self.resolve_zones();
+                    if is_showing {
+                        self.draw_zones(self.win_id)?;
+                    }
+                }
+                Event::XinputRawKeyRelease(e)
+                    if is_showing && self.activation().is_modifier_key(e.detail) =>
+                {
+                    is_showing = false;
+                    self.hide()?;
+                }
+                Event::XinputRawButtonRelease(e)
+                    if is_showing && e.detail == self.activation().drop_button =>
+                {
+                    if activated {
+                        if let Some(active_win) = win {
+                            self.snap_to_zone(active_win)?;
+                            win = None;
+                        } else {
+                            println!("Got no win outer");
                         }
                     }
+                    is_showing = false;
+                    self.hide()?;
                 }
                 _ => {}
             }
         }
     }
 
-    fn button_pressed(&self, but: KeyButMask) -> Result<bool, ReplyOrIdError> {
+    fn activation(&self) -> &ActivationKeys {
+        self.activation.as_ref().expect("Activation keys not setup")
+    }
+
+    fn modifiers_pressed(&self) -> Result<bool, ReplyOrIdError> {
         if let Ok(reply) = self.conn.query_pointer(self.screen.root)?.reply() {
-            Ok(reply.mask & but != KeyButMask::from(0_u16))
+            Ok(self.activation().is_active(reply.mask))
         } else {
             Ok(false)
         }
@@ -309,28 +825,148 @@ impl<C: Connection> Overlay<C> {
     }
 
     fn snap_to_zone(&mut self, win: u32) -> Result<(), ReplyOrIdError> {
-        if let Some(zone) = self.active_zone {
-            let zone = &self.zones[zone];
-            let conf = ConfigureWindowAux::new()
-                .x(i32::from(zone.x))
-                .y(i32::from(zone.y))
-                .width(u32::try_from(zone.width).unwrap())
-                .height(u32::try_from(zone.height).unwrap())
-                .stack_mode(StackMode::ABOVE);
-
-            self.disable_window_padding(win)?;
-
-            self.conn.change_window_attributes(
-                win,
-                &ChangeWindowAttributesAux::new().win_gravity(Gravity::NORTH_WEST),
-            )?;
-            self.conn.configure_window(win, &conf)?;
-            self.conn.flush()?;
+        if let Some(index) = self.active_zone {
+            let rect = zone_rect(&self.zones[index].zone);
+            if self.register(win, self.zone_ref(index), rect)? {
+                self.configure_into(win, rect)?;
+                self.conn.flush()?;
+            }
             self.active_zone = None;
         }
         Ok(())
     }
 
+    fn configure_into(&self, win: Window, rect: Rectangle) -> Result<(), ReplyOrIdError> {
+        let conf = ConfigureWindowAux::new()
+            .x(i32::from(rect.x))
+            .y(i32::from(rect.y))
+            .width(u32::from(rect.width))
+            .height(u32::from(rect.height))
+            .stack_mode(StackMode::ABOVE);
+
+        self.disable_window_padding(win)?;
+
+        self.conn.change_window_attributes(
+            win,
+            &ChangeWindowAttributesAux::new().win_gravity(Gravity::NORTH_WEST),
+        )?;
+        self.conn.configure_window(win, &conf)?;
+        Ok(())
+    }
+
+    /// Records that `win` is snapped into `zone_ref` at `rect`, together with the size it has
+    /// before. A window moved from one zone to another keeps the size it had before the
+    /// first one.
+    /// Returns false if the window is gone already.
+    fn register(
+        &mut self,
+        win: Window,
+        zone_ref: ZoneRef,
+        rect: Rectangle,
+    ) -> Result<bool, ReplyOrIdError> {
+        let (width, height) = match self.snapped.get(&win) {
+            Some(snapped) => (snapped.original_width, snapped.original_height),
+            None => {
+                let Ok(geometry) = self.conn.get_geometry(win)?.reply() else {
+                    return Ok(false);
+                };
+                (geometry.width, geometry.height)
+            }
+        };
+        let snapped = SnappedWindow::new(zone_ref.clone(), width, height, rect);
+        self.write_snapped(win, &snapped)?;
+        self.snapped.insert(win, snapped);
+
+        if let Ok(info) = util::get_window_info(&*self.conn, win) {
+            self.update_state(|state| state.remember(&info, zone_ref));
+        }
+        Ok(true)
+    }
+
+    fn load_state(&self) -> State {
+        let Some(path) = &self.state_path else {
+            return State::default();
+        };
+        state::load_state_file(path).unwrap_or_else(|e| -> State {
+            eprintln!("Ignoring window placements: {}", e);
+            State::default()
+        })
+    }
+
+    fn update_state(&self, update: impl FnOnce(&mut State)) {
+        let Some(path) = &self.state_path else {
+            return;
+        };
+        let mut state = self.load_state();
+        update(&mut state);
+        if let Err(e) = state::save_state_file(path, &state) {
+            eprintln!("Could not save window placements: {}", e);
+        }
+    }
+
+    /// Client window in the top level `win`, the one that keeps its properties when the
+    /// window manager reparents it, for example across a restart of the window manager.
+    fn client(&self, win: Window) -> Window {
+        // the window may be gone, the request on it then fails on its own
+        util::find_client(&*self.conn, win).unwrap_or(win)
+    }
+
+    /// Stores the zone and geometry of a snapped window on its client window, so they
+    /// survive a restart of the daemon.
+    fn write_snapped(&self, win: Window, snapped: &SnappedWindow) -> Result<(), ReplyOrIdError> {
+        let client = self.client(win);
+        self.conn.change_property8(
+            PropMode::REPLACE,
+            client,
+            self.atoms.lancy_zones_zone,
+            AtomEnum::STRING,
+            &snapped.zone.to_property(),
+        )?;
+        self.conn.change_property32(
+            PropMode::REPLACE,
+            client,
+            self.atoms.lancy_zones_geometry,
+            AtomEnum::CARDINAL,
+            &snapped.to_property(),
+        )?;
+        Ok(())
+    }
+
+    /// Forgets a snapped window once it leaves its zone. One dragged out without the
+    /// activation modifiers gets its original size back.
+    fn follow_snapped(&mut self, e: &ConfigureNotifyEvent) -> Result<(), ReplyOrIdError> {
+        let Some(snapped) = self.snapped.get_mut(&e.window) else {
+            return Ok(());
+        };
+        match snapped.update(e) {
+            Change::Kept => return Ok(()),
+            Change::Settled => {
+                let snapped = snapped.clone();
+                return self.write_snapped(e.window, &snapped);
+            }
+            Change::DraggedOut if self.config.restore_size => {
+                self.conn.configure_window(
+                    e.window,
+                    &ConfigureWindowAux::new()
+                        .width(u32::from(snapped.original_width))
+                        .height(u32::from(snapped.original_height)),
+                )?;
+            }
+            Change::DraggedOut | Change::Resized => {}
+        }
+        self.snapped.remove(&e.window);
+        if let Ok(info) = util::get_window_info(&*self.conn, e.window) {
+            self.update_state(|state| state.forget(&info));
+        }
+        let client = self.client(e.window);
+        self.conn
+            .delete_property(client, self.atoms.lancy_zones_zone)?;
+        self.conn
+            .delete_property(client, self.atoms.lancy_zones_geometry)?;
+        self.conn.flush()?;
+        Ok(())
+    }
+
     fn disable_window_padding(&self, win: u32) -> Result<(), ReplyOrIdError> {
         let no_extents = [0, 0, 0, 0];
         let _ = self.conn.change_property32(
@@ -355,9 +991,10 @@ impl<C: Connection> Overlay<C> {
         let mut zone_area_min = u32::MAX;
 
         for i in 0..self.zones.len() {
-            if self.zones[i].is_inside(x, y) {
-                let dist_sqr = self.zones[i].get_sqr_dist_to(x, y);
-                let zone_area = self.zones[i].get_area();
+            let zone = &self.zones[i].zone;
+            if zone.is_inside(x, y) {
+                let dist_sqr = zone.get_sqr_dist_to(x, y);
+                let zone_area = zone.get_area();
                 if dist_sqr < dist_sqr_min || dist_sqr == dist_sqr_min && zone_area < zone_area_min
                 {
                     self.active_zone = Some(i);
@@ -367,54 +1004,31 @@ impl<C: Connection> Overlay<C> {
             }
         }
 
-        self.draw_zones(
-            self.win_id,
-            self.colors.as_ref().unwrap().white.gcontext(),
-            self.colors.as_ref().unwrap().black.gcontext(),
-        )
-        .unwrap();
-    }
-
-    fn draw_zones(&self, win_id: Window, c1: Gcontext, c2: Gcontext) -> Result<(), ReplyOrIdError> {
-        for zone in &self.zones {
-            let top = Rectangle {
-                x: zone.x,
-                y: zone.y,
-                width: zone.width as u16,
-                height: self.config.line_thickness,
-            };
-
-            let left = Rectangle {
-                x: zone.x,
-                y: zone.y,
-                width: self.config.line_thickness,
-                height: zone.height as u16,
-            };
-
-            let right = Rectangle {
-                x: zone.x + zone.width - self.config.line_thickness as i16,
-                y: zone.y,
-                width: self.config.line_thickness,
-                height: zone.height as u16,
-            };
+        self.draw_zones(self.win_id).unwrap();
+    }
 
-            let bottom = Rectangle {
-                x: zone.x,
-                y: zone.y + zone.height - self.config.line_thickness as i16,
-                width: zone.width as u16,
-                height: self.config.line_thickness,
-            };
+    fn draw_zones(&self, win_id: Window) -> Result<(), ReplyOrIdError> {
+        let colors = self.colors.as_ref().expect("Colors not setup");
 
-            let bg = Rectangle {
-                x: zone.x,
-                y: zone.y,
-                width: zone.width as u16,
-                height: zone.height as u16,
+        for ThemedZone {
+            zone, theme, label, ..
+        } in &self.zones
+        {
+            let fill = if zone.name.is_empty() {
+                Part::Background
+            } else {
+                Part::ZoneFill
             };
-
-            self.conn.poly_fill_rectangle(win_id, c2, &[bg])?;
-            self.conn
-                .poly_fill_rectangle(win_id, c1, &[top, left, right, bottom])?;
+            colors.fill_rectangles(win_id, *theme, fill, &[zone_rect(zone)])?;
+            colors.fill_rectangles(
+                win_id,
+                *theme,
+                Part::ZoneBorder,
+                &border_rects(zone, colors.line_thickness(*theme)),
+            )?;
+            if let Some(label) = label {
+                colors.fill_rectangles(win_id, *theme, Part::Label, &label_rects(zone, label))?;
+            }
         }
 
         self.draw_active_zone()?;
@@ -426,17 +1040,188 @@ impl<C: Connection> Overlay<C> {
         let colors = self.colors.as_ref().expect("Colors not setup");
 
         if let Some(zone) = self.active_zone {
-            let zone = &self.zones[zone];
-            let rect = Rectangle {
-                x: zone.x,
-                y: zone.y,
-                width: zone.width as u16,
-                height: zone.height as u16,
+            let ThemedZone {
+                zone, theme, label, ..
+            } = &self.zones[zone];
+            colors.fill_rectangles(self.win_id, *theme, Part::ActiveFill, &[zone_rect(zone)])?;
+            colors.fill_rectangles(
+                self.win_id,
+                *theme,
+                Part::ActiveBorder,
+                &border_rects(zone, colors.line_thickness(*theme)),
+            )?;
+            if let Some(label) = label {
+                colors.fill_rectangles(
+                    self.win_id,
+                    *theme,
+                    Part::ActiveLabel,
+                    &label_rects(zone, label),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The connected monitors with the configs of the matching profile or the stored assignments.
+/// `profile` is the name of the profile in use, the switch to another one is logged.
+fn detect_monitors<C: Connection>(
+    conn: &C,
+    root: Window,
+    config: &Config,
+    profile: &mut Option<String>,
+) -> Result<Vec<Monitor>, ReplyOrIdError> {
+    let detected = util::get_monitors(conn, root)?;
+    let matching = config
+        .matching_profile(&detected)
+        .map(|profile| profile.name.clone());
+    if matching != *profile {
+        match &matching {
+            Some(name) => println!("Using profile {}", name),
+            None => println!("No profile matches, using the stored assignments"),
+        }
+        *profile = matching;
+    }
+    Ok(config.assign_detected(detected))
+}
+
+/// Resolves the zones of `monitors` into root window coordinates, together with the
+/// themes they refer to. `work_areas` holds the work area of each monitor, `desktop` and
+/// `desktop_name` select desktop specific configs.
+fn build_zones(
+    config: &Config,
+    monitors: &[Monitor],
+    work_areas: &[Area],
+    desktop: u32,
+    desktop_name: Option<&str>,
+) -> (Vec<ThemedZone>, Vec<Theme>) {
+    // theme 0 is the global one, monitor config i uses theme i + 1
+    let mut themes = vec![config.theme_for(None)];
+    themes.extend(
+        config
+            .monitor_configs
+            .iter()
+            .map(|mc| config.theme_for(Some(mc))),
+    );
+
+    let mut zones = Vec::new();
+    for (index, (monitor, work_area)) in monitors.iter().zip(work_areas).enumerate() {
+        let mc_index = monitor
+            .config_for_desktop(desktop, desktop_name)
+            .and_then(|name| config.monitor_configs.iter().position(|mc| mc.name == name));
+        let theme = mc_index.map_or(0, |i| i + 1);
+
+        // add background zone for correct rendering
+        zones.push(ThemedZone {
+            zone: ResolvedZone {
+                name: "".to_string(),
+                x: monitor.x,
+                y: monitor.y,
+                width: monitor.width as i16,
+                height: monitor.height as i16,
+            },
+            monitor: index,
+            layout: None,
+            theme,
+            label: None,
+        });
+        if let Some(i) = mc_index {
+            let mc = &config.monitor_configs[i];
+            let area = if mc.work_area {
+                *work_area
+            } else {
+                monitor.area()
             };
+            for (zone_index, zone) in mc.zones.iter().enumerate() {
+                let trans_zone = zone
+                    .resolve(area.width, area.height)
+                    .translate(area.x, area.y);
+                zones.push(ThemedZone {
+                    zone: trans_zone,
+                    monitor: index,
+                    layout: Some(i),
+                    theme,
+                    label: config.zone_labels.label(zone_index, zone),
+                });
+            }
+        }
+    }
 
-            self.conn
-                .poly_fill_rectangle(self.win_id, colors.white.gcontext(), &[rect])?;
+    // Sort by biggest area first (ording::less). This helps rendering of zones that cover
+    // eachother, because the common case is a zone covered by farction of itself
+    zones.sort_by(|a, b| -> Ordering {
+        let a = a.zone.get_area();
+        let b = b.zone.get_area();
+        if a > b {
+            Ordering::Less
+        } else if a < b {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
         }
-        Ok(())
+    });
+
+    (zones, themes)
+}
+
+fn zone_rect(zone: &ResolvedZone) -> Rectangle {
+    Rectangle {
+        x: zone.x,
+        y: zone.y,
+        width: zone.width as u16,
+        height: zone.height as u16,
+    }
+}
+
+/// `label` centred in the zone, scaled to take up at most half its width and a quarter of
+/// its height. Labels that don't fit even at the smallest scale are left out.
+fn label_rects(zone: &ResolvedZone, label: &str) -> Vec<Rectangle> {
+    let (width, height) = font::text_size(label, 1);
+    if width == 0 {
+        return Vec::new();
     }
+    let max_width = (zone.width.max(0) as u16) / 2;
+    let max_height = (zone.height.max(0) as u16) / 4;
+    let scale = (max_width / width).min(max_height / height);
+    if scale == 0 {
+        return Vec::new();
+    }
+
+    let (width, height) = font::text_size(label, scale);
+    let x = zone.x + (zone.width - width as i16) / 2;
+    let y = zone.y + (zone.height - height as i16) / 2;
+    font::text_rects(label, x, y, scale)
+}
+
+/// Top, left, right and bottom edge of the zone.
+fn border_rects(zone: &ResolvedZone, thickness: u16) -> [Rectangle; 4] {
+    let top = Rectangle {
+        x: zone.x,
+        y: zone.y,
+        width: zone.width as u16,
+        height: thickness,
+    };
+
+    let left = Rectangle {
+        x: zone.x,
+        y: zone.y,
+        width: thickness,
+        height: zone.height as u16,
+    };
+
+    let right = Rectangle {
+        x: zone.x + zone.width - thickness as i16,
+        y: zone.y,
+        width: thickness,
+        height: zone.height as u16,
+    };
+
+    let bottom = Rectangle {
+        x: zone.x,
+        y: zone.y + zone.height - thickness as i16,
+        width: zone.width as u16,
+        height: thickness,
+    };
+
+    [top, left, right, bottom]
 }
@@ -0,0 +1,103 @@
+use x11rb::protocol::xproto::{ConfigureNotifyEvent, Rectangle};
+
+use lancy_zones::state::ZoneRef;
+
+/// A window the overlay snapped into a zone, with the size it had before.
+#[derive(Debug, Clone)]
+pub struct SnappedWindow {
+    pub zone: ZoneRef,
+    pub original_width: u16,
+    pub original_height: u16,
+    /// Geometry of the window in the zone.
+    pub geometry: Rectangle,
+    /// False until the first configure after the snap reported where the window really
+    /// ended up, window managers may adjust the requested geometry a little.
+    pub settled: bool,
+}
+
+/// What a configure of a snapped window means for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// Still in its zone.
+    Kept,
+    /// First configure after the snap, the zone geometry now is where the window really is.
+    Settled,
+    /// Moved out of its zone with the zone's size, the original size should come back.
+    DraggedOut,
+    /// Resized by something else, the zone geometry no longer applies.
+    Resized,
+}
+
+impl SnappedWindow {
+    pub fn new(
+        zone: ZoneRef,
+        original_width: u16,
+        original_height: u16,
+        geometry: Rectangle,
+    ) -> Self {
+        SnappedWindow {
+            zone,
+            original_width,
+            original_height,
+            geometry,
+            settled: false,
+        }
+    }
+
+    /// Takes the geometry of the zone after it changed. The window settles again with the
+    /// configure that moves it there.
+    pub fn resnap(&mut self, geometry: Rectangle) {
+        self.geometry = geometry;
+        self.settled = false;
+    }
+
+    pub fn update(&mut self, e: &ConfigureNotifyEvent) -> Change {
+        if !self.settled {
+            self.geometry = Rectangle {
+                x: e.x,
+                y: e.y,
+                width: e.width,
+                height: e.height,
+            };
+            self.settled = true;
+            return Change::Settled;
+        }
+
+        if (e.width, e.height) != (self.geometry.width, self.geometry.height) {
+            Change::Resized
+        } else if (e.x, e.y) != (self.geometry.x, self.geometry.y) {
+            Change::DraggedOut
+        } else {
+            Change::Kept
+        }
+    }
+
+    /// Value of the geometry property that keeps the state across daemon restarts, the zone
+    /// is stored in a property of its own.
+    pub fn to_property(&self) -> [u32; 6] {
+        [
+            self.original_width as u32,
+            self.original_height as u32,
+            self.geometry.x as u32,
+            self.geometry.y as u32,
+            self.geometry.width as u32,
+            self.geometry.height as u32,
+        ]
+    }
+
+    pub fn from_property(zone: ZoneRef, values: &[u32]) -> Option<Self> {
+        let v: &[u32; 6] = values.get(..6)?.try_into().ok()?;
+        Some(SnappedWindow {
+            zone,
+            original_width: v[0] as u16,
+            original_height: v[1] as u16,
+            geometry: Rectangle {
+                x: v[2] as i16,
+                y: v[3] as i16,
+                width: v[4] as u16,
+                height: v[5] as u16,
+            },
+            settled: true,
+        })
+    }
+}
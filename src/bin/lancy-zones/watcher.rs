@@ -0,0 +1,63 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use inotify::{Inotify, WatchMask};
+use x11rb::{
+    connection::Connection,
+    errors::ReplyOrIdError,
+    protocol::xproto::{Atom, ClientMessageEvent, ConnectionExt as _, EventMask, Window},
+};
+
+/// Watches the config file and sends a `reload` client message to `win_id` whenever it
+/// was written. The directory is watched instead of the file because saves replace the
+/// file by renaming a temp file over it.
+pub fn spawn(path: &Path, win_id: Window, reload: Atom) -> std::io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let file_name = path.file_name().map(OsString::from);
+
+    let mut inotify = Inotify::init()?;
+    inotify.watches().add(
+        &dir,
+        WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE,
+    )?;
+
+    thread::spawn(move || {
+        let (conn, _) = match x11rb::connect(None) {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("Config watcher could not connect to X server: {}", e);
+                return;
+            }
+        };
+        let mut buffer = [0; 4096];
+        loop {
+            let events = match inotify.read_events_blocking(&mut buffer) {
+                Ok(events) => events,
+                Err(e) => {
+                    eprintln!("Stopped watching {}: {}", dir.display(), e);
+                    return;
+                }
+            };
+            let changed = events
+                .into_iter()
+                .any(|event| event.name.map(OsString::from) == file_name);
+            if changed && let Err(e) = notify(&conn, win_id, reload) {
+                eprintln!("Failed to request config reload: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn notify<C: Connection>(conn: &C, win_id: Window, reload: Atom) -> Result<(), ReplyOrIdError> {
+    let event = ClientMessageEvent::new(32, win_id, reload, [0_u32; 5]);
+    conn.send_event(false, win_id, EventMask::NO_EVENT, event)?;
+    conn.flush()?;
+    Ok(())
+}
@@ -1,28 +1,535 @@
 use core::fmt;
 use std::{
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
     fs::{self, File, read},
-    io::Write,
+    io::{self, Write},
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use x11rb::{
     connection::Connection,
-    protocol::{randr, xproto::Window},
+    errors::{ConnectError, ReplyOrIdError},
+    protocol::xproto::{KeyButMask, Keysym, Window},
 };
 
-use crate::util;
+use regex::Regex;
 
-pub fn get_config_path() -> PathBuf {
-    Path::new("~/.config/lancy-zones/config.json").to_path_buf()
+use crate::{
+    edid::Edid,
+    layout::LayoutError,
+    util,
+    window::{WindowInfo, WindowType},
+    workarea::Area,
+};
+
+/// Environment variable that overrides the config file location.
+pub const CONFIG_PATH_ENV: &str = "LANCY_ZONES_CONFIG";
+
+const CONFIG_DIR_NAME: &str = "lancy-zones";
+const CONFIG_FILE_NAME: &str = "config.json";
+
+/// Schema version written by this build. Bump it together with a new entry in [`MIGRATIONS`].
+pub const CONFIG_VERSION: u32 = 12;
+
+/// Number of previous config versions kept as `config.json.1..N`.
+pub const BACKUP_COUNT: usize = 5;
+
+/// Resolves the config file location.
+///
+/// Lookup order is: `cli_path` (the `--config` flag), `$LANCY_ZONES_CONFIG`,
+/// `$XDG_CONFIG_HOME/lancy-zones/config.json` and finally
+/// `$HOME/.config/lancy-zones/config.json`.
+/// Returns `None` if none of them is set.
+pub fn resolve_config_path(cli_path: Option<&Path>) -> Option<PathBuf> {
+    if let Some(path) = cli_path {
+        return Some(path.to_path_buf());
+    }
+    if let Some(path) = non_empty_var(CONFIG_PATH_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    get_config_path()
+}
+
+/// Default config file location following the XDG base directory spec.
+pub fn get_config_path() -> Option<PathBuf> {
+    // relative values for XDG_CONFIG_HOME are invalid per spec and get ignored
+    let config_home = non_empty_var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| non_empty_var("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+pub(crate) fn non_empty_var(key: &str) -> Option<OsString> {
+    env::var_os(key).filter(|val| !val.is_empty())
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        source: serde_json::Error,
+    },
+    StateIo {
+        path: PathBuf,
+        source: io::Error,
+    },
+    StateParse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    Connect(ConnectError),
+    X11(ReplyOrIdError),
+    MissingMonitor(String),
+    MissingLayout(String),
+    LayoutExists(String),
+    MissingZone {
+        config: String,
+        zone: String,
+    },
+    ZoneExists {
+        config: String,
+        zone: String,
+    },
+    MixedUnits {
+        zone: String,
+        length: Length,
+        offset: Length,
+    },
+    OffsetOverflow {
+        zone: String,
+        length: Length,
+        offset: Length,
+    },
+    MissingBackup(PathBuf),
+    MissingRule(String),
+    InvalidPattern {
+        pattern: String,
+        source: regex::Error,
+    },
+    Unassigned(String),
+    Layout(LayoutError),
+    Invalid(Vec<Diagnostic>),
+    UnsupportedVersion {
+        path: PathBuf,
+        version: u64,
+    },
+}
+
+impl ConfigError {
+    fn io(path: &Path, source: io::Error) -> Self {
+        ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    fn parse(path: &Path, source: serde_json::Error) -> Self {
+        ConfigError::Parse {
+            path: path.to_path_buf(),
+            line: source.line(),
+            column: source.column(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => {
+                write!(
+                    f,
+                    "could not access config file {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            // serde_json already appends the line and column to its message
+            ConfigError::Parse { path, source, .. } => {
+                write!(
+                    f,
+                    "could not parse config file {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            ConfigError::StateIo { path, source } => {
+                write!(
+                    f,
+                    "could not access state file {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            ConfigError::StateParse { path, source } => {
+                write!(
+                    f,
+                    "could not parse state file {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            ConfigError::Connect(e) => write!(f, "could not connect to X server: {}", e),
+            ConfigError::X11(e) => write!(f, "could not query monitors from X server: {}", e),
+            ConfigError::MissingMonitor(name) => write!(f, "monitor {} does not exist", name),
+            ConfigError::MissingLayout(name) => write!(f, "config {} does not exist", name),
+            ConfigError::LayoutExists(name) => write!(f, "config {} already exists", name),
+            ConfigError::MissingZone { config, zone } => {
+                write!(f, "zone {} does not exist in config {}", zone, config)
+            }
+            ConfigError::ZoneExists { config, zone } => {
+                write!(f, "zone {} already exists in config {}", zone, config)
+            }
+            ConfigError::MixedUnits {
+                zone,
+                length,
+                offset,
+            } => write!(
+                f,
+                "cannot move {} of zone {} by {}, units differ",
+                length, zone, offset
+            ),
+            ConfigError::OffsetOverflow {
+                zone,
+                length,
+                offset,
+            } => write!(
+                f,
+                "cannot move {} of zone {} by {}, the result is out of range",
+                length, zone, offset
+            ),
+            ConfigError::UnsupportedVersion { path, version } => write!(
+                f,
+                "config file {} has version {}, but this build only supports up to {}",
+                path.display(),
+                version,
+                CONFIG_VERSION
+            ),
+            ConfigError::Unassigned(name) => {
+                write!(f, "config {} is not assigned to any monitor", name)
+            }
+            ConfigError::Layout(e) => write!(f, "invalid layout: {}", e),
+            ConfigError::Invalid(diagnostics) => write!(
+                f,
+                "config is invalid ({} error(s))",
+                diagnostics.iter().filter(|d| d.is_error()).count()
+            ),
+            ConfigError::MissingBackup(path) => {
+                write!(f, "backup {} does not exist", path.display())
+            }
+            ConfigError::MissingRule(name) => write!(f, "rule {} does not exist", name),
+            ConfigError::InvalidPattern { pattern, source } => {
+                write!(f, "invalid title pattern {:?}: {}", pattern, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+            ConfigError::StateIo { source, .. } => Some(source),
+            ConfigError::StateParse { source, .. } => Some(source),
+            ConfigError::Connect(e) => Some(e),
+            ConfigError::X11(e) => Some(e),
+            ConfigError::Layout(e) => Some(e),
+            ConfigError::InvalidPattern { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<LayoutError> for ConfigError {
+    fn from(e: LayoutError) -> Self {
+        ConfigError::Layout(e)
+    }
+}
+
+impl From<ConnectError> for ConfigError {
+    fn from(e: ConnectError) -> Self {
+        ConfigError::Connect(e)
+    }
+}
+
+impl From<ReplyOrIdError> for ConfigError {
+    fn from(e: ReplyOrIdError) -> Self {
+        ConfigError::X11(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found by [`Config::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Which part of the config is affected, e.g. `config DP-0, zone main`.
+    pub location: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(location: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            location: location.into(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(location: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            location: location.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", severity, self.location, self.message)
+    }
+}
+
+/// An RGB colour, stored as `#rrggbb` in the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color { r: 0, g: 0, b: 0 };
+    pub const WHITE: Color = Color {
+        r: 0xff,
+        g: 0xff,
+        b: 0xff,
+    };
+
+    /// The colour as 16 bit per channel values, as expected by the X colormap.
+    pub fn to_rgb16(self) -> (u16, u16, u16) {
+        let scale = |c: u8| c as u16 * 0x101;
+        (scale(self.r), scale(self.g), scale(self.b))
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid colour {:?}, expected e.g. #1e1e2e", s);
+        let hex = s.strip_prefix('#').ok_or_else(invalid)?;
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        Ok(Color {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Colours and border thickness of the overlay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    pub zone_fill: Color,
+    pub zone_border: Color,
+    pub active_fill: Color,
+    pub active_border: Color,
+    /// Fill of monitor areas not covered by a zone.
+    pub background: Color,
+    pub line_thickness: u16,
+    /// Text colour of the zone labels, see [`ZoneLabels`].
+    pub label: Color,
+    pub active_label: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            zone_fill: Color::BLACK,
+            zone_border: Color::WHITE,
+            active_fill: Color::WHITE,
+            active_border: Color::WHITE,
+            background: Color::BLACK,
+            line_thickness: 3,
+            label: Color::WHITE,
+            active_label: Color::BLACK,
+        }
+    }
+}
+
+impl Theme {
+    pub fn with_override(&self, o: &ThemeOverride) -> Theme {
+        Theme {
+            zone_fill: o.zone_fill.unwrap_or(self.zone_fill),
+            zone_border: o.zone_border.unwrap_or(self.zone_border),
+            active_fill: o.active_fill.unwrap_or(self.active_fill),
+            active_border: o.active_border.unwrap_or(self.active_border),
+            background: o.background.unwrap_or(self.background),
+            line_thickness: o.line_thickness.unwrap_or(self.line_thickness),
+            label: o.label.unwrap_or(self.label),
+            active_label: o.active_label.unwrap_or(self.active_label),
+        }
+    }
+}
+
+/// Per [`MonitorConfig`] replacements for parts of the global [`Theme`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zone_fill: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zone_border: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_fill: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_border: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_thickness: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_label: Option<Color>,
+}
+
+impl ThemeOverride {
+    pub fn is_empty(&self) -> bool {
+        *self == ThemeOverride::default()
+    }
+}
+
+/// What the overlay writes into each zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZoneLabels {
+    /// Index and name, e.g. `2 main`.
+    #[default]
+    Both,
+    Names,
+    /// 1-based position of the zone in its config.
+    Indices,
+    None,
+}
+
+impl ZoneLabels {
+    /// Label of the zone at `index` in its config.
+    pub fn label(self, index: usize, zone: &Zone) -> Option<String> {
+        match self {
+            ZoneLabels::Both => Some(format!("{} {}", index + 1, zone.name)),
+            ZoneLabels::Names => Some(zone.name.clone()),
+            ZoneLabels::Indices => Some((index + 1).to_string()),
+            ZoneLabels::None => None,
+        }
+    }
+}
+
+/// Left mouse button.
+pub const DEFAULT_DROP_BUTTON: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Modifier {
+    Shift,
+    Control,
+    Alt,
+    Super,
+}
+
+impl Modifier {
+    pub fn mask(self) -> KeyButMask {
+        match self {
+            Modifier::Shift => KeyButMask::SHIFT,
+            Modifier::Control => KeyButMask::CONTROL,
+            Modifier::Alt => KeyButMask::MOD1,
+            Modifier::Super => KeyButMask::MOD4,
+        }
+    }
+
+    /// Left and right keysym of the modifier.
+    pub fn keysyms(self) -> [Keysym; 2] {
+        match self {
+            Modifier::Shift => [0xffe1, 0xffe2],
+            Modifier::Control => [0xffe3, 0xffe4],
+            Modifier::Alt => [0xffe9, 0xffea],
+            Modifier::Super => [0xffeb, 0xffec],
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
+    pub version: u32,
     pub monitors: Vec<Monitor>,
     pub monitor_configs: Vec<MonitorConfig>,
     pub alpha: f32,
-    pub line_thickness: u16,
+    pub theme: Theme,
+    /// Modifiers that all have to be held while dragging a window to show the overlay.
+    pub activation_modifiers: Vec<Modifier>,
+    /// Mouse button (X button number) whose release drops the window into the active zone.
+    pub drop_button: u8,
+    pub zone_labels: ZoneLabels,
+    /// Assignments that replace the ones stored on [`Config::monitors`] while exactly
+    /// their set of outputs is connected.
+    pub profiles: Vec<Profile>,
+    /// Give a snapped window its original size back when it is dragged out of its zone
+    /// without the activation modifiers.
+    pub restore_size: bool,
+    /// Zones new windows are snapped into, the first matching rule wins.
+    pub rules: Vec<Rule>,
 }
 
 impl Config {
@@ -38,16 +545,202 @@ impl Config {
             .find(|cfg| -> bool { cfg.name == mc_name })
     }
 
-    pub fn refresh_all_global_monitor_pos<C: Connection>(&mut self, conn: &C, root_window: Window) {
+    /// The config assigned to `monitor`, if any.
+    pub fn get_assigned_config(&self, monitor: &Monitor) -> Option<&MonitorConfig> {
+        self.get_monitor_config(monitor.config.as_deref()?)
+    }
+
+    /// The global theme with the overrides of `mc` applied.
+    pub fn theme_for(&self, mc: Option<&MonitorConfig>) -> Theme {
+        match mc {
+            Some(mc) => self.theme.with_override(&mc.theme),
+            None => self.theme.clone(),
+        }
+    }
+
+    /// All monitors the config `mc_name` is assigned to.
+    pub fn monitors_using<'a>(&'a self, mc_name: &'a str) -> impl Iterator<Item = &'a Monitor> {
+        self.monitors
+            .iter()
+            .filter(move |monitor| monitor.uses(mc_name))
+    }
+
+    /// The first profile made for exactly the outputs in `detected`.
+    pub fn matching_profile(&self, detected: &[Monitor]) -> Option<&Profile> {
+        self.profiles
+            .iter()
+            .find(|profile| -> bool { profile.matches(detected) })
+    }
+
+    /// The monitors currently detected by X, with the configs assigned to them here.
+    /// A matching profile decides the assignments, otherwise they come from
+    /// [`Config::monitors`]. Monitors that are not connected anymore are left out, new ones
+    /// have no config.
+    pub fn assign_detected(&self, detected: Vec<Monitor>) -> Vec<Monitor> {
+        let profile = self.matching_profile(&detected).cloned();
+        let mut monitors = self.assign_stored(detected);
+        if let Some(profile) = profile {
+            for monitor in &mut monitors {
+                monitor.config = profile.assignment(monitor).map(str::to_string);
+            }
+        }
+        monitors
+    }
+
+    /// `detected` with the assignments stored on [`Config::monitors`], ignoring profiles.
+    /// Desktop specific assignments are kept either way.
+    pub fn assign_stored(&self, mut detected: Vec<Monitor>) -> Vec<Monitor> {
+        for monitor in &mut detected {
+            let known = monitor.find_in(&self.monitors);
+            monitor.config = known.and_then(|known| known.config.clone());
+            monitor.desktops = known
+                .map(|known| known.desktops.clone())
+                .unwrap_or_default();
+        }
+        detected
+    }
+
+    /// Checks the config for problems the overlay would trip over.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        if !(0.0..=1.0).contains(&self.alpha) {
+            diagnostics.push(Diagnostic::error(
+                "alpha",
+                format!("{} is outside of 0..1", self.alpha),
+            ));
+        }
+
+        if self.activation_modifiers.is_empty() {
+            diagnostics.push(Diagnostic::error(
+                "activation_modifiers",
+                "at least one modifier is required, otherwise every window move shows the overlay",
+            ));
+        }
+        if self.drop_button == 0 {
+            diagnostics.push(Diagnostic::error("drop_button", "buttons start at 1"));
+        }
+
+        for monitor in &self.monitors {
+            if let Some(mc_name) = &monitor.config
+                && self.get_monitor_config(mc_name).is_none()
+            {
+                diagnostics.push(Diagnostic::error(
+                    format!("monitor {}", monitor.name),
+                    format!("assigned config {} does not exist", mc_name),
+                ));
+            }
+            for assignment in &monitor.desktops {
+                if self.get_monitor_config(&assignment.config).is_none() {
+                    diagnostics.push(Diagnostic::error(
+                        format!("monitor {}, desktop {}", monitor.name, assignment.desktop),
+                        format!("assigned config {} does not exist", assignment.config),
+                    ));
+                }
+            }
+        }
+
+        for (i, profile) in self.profiles.iter().enumerate() {
+            let location = format!("profile {}", profile.name);
+            if self.profiles[..i]
+                .iter()
+                .any(|other| other.name == profile.name)
+            {
+                diagnostics.push(Diagnostic::error(
+                    location.clone(),
+                    "profile name is used more than once",
+                ));
+            }
+            for (monitor, mc_name) in &profile.assignments {
+                if self.get_monitor_config(mc_name).is_none() {
+                    diagnostics.push(Diagnostic::error(
+                        location.clone(),
+                        format!("config {} assigned to {} does not exist", mc_name, monitor),
+                    ));
+                }
+                if !profile.outputs.contains(monitor) {
+                    diagnostics.push(Diagnostic::warning(
+                        location.clone(),
+                        format!("{} is not one of the profile's outputs", monitor),
+                    ));
+                }
+            }
+        }
+
+        for (i, rule) in self.rules.iter().enumerate() {
+            let location = format!("rule {}", rule.name);
+            if self.rules[..i].iter().any(|other| other.name == rule.name) {
+                diagnostics.push(Diagnostic::error(
+                    location.clone(),
+                    "rule name is used more than once",
+                ));
+            }
+            if rule.is_catch_all() {
+                diagnostics.push(Diagnostic::error(
+                    location.clone(),
+                    "rule matches every window, set at least one of instance, class, title and window_type",
+                ));
+            }
+            if let Err(e) = rule.title_regex() {
+                diagnostics.push(Diagnostic::error(location.clone(), e.to_string()));
+            }
+            match self.get_monitor_config(&rule.config) {
+                None => diagnostics.push(Diagnostic::error(
+                    location.clone(),
+                    format!("config {} does not exist", rule.config),
+                )),
+                Some(mc) if mc.get_zone(&rule.zone).is_none() => {
+                    diagnostics.push(Diagnostic::error(
+                        location.clone(),
+                        format!(
+                            "zone {} does not exist in config {}",
+                            rule.zone, rule.config
+                        ),
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+
+        for (i, mc) in self.monitor_configs.iter().enumerate() {
+            if self.monitor_configs[..i]
+                .iter()
+                .any(|other| other.name == mc.name)
+            {
+                diagnostics.push(Diagnostic::error(
+                    format!("config {}", mc.name),
+                    "config name is used more than once",
+                ));
+            }
+            let assigned: Vec<&Monitor> = self.monitors_using(&mc.name).collect();
+            mc.validate(&assigned, &mut diagnostics);
+        }
+
+        diagnostics
+    }
+
+    pub fn refresh_all_global_monitor_pos<C: Connection>(
+        &mut self,
+        conn: &C,
+        root_window: Window,
+    ) -> Result<(), ConfigError> {
+        let connected = util::get_monitors(conn, root_window)?;
         for monitor in &mut self.monitors {
-            monitor.refresh_global_pos(conn, root_window);
+            monitor.refresh_global_pos(&connected);
         }
+        Ok(())
     }
 
-    pub fn refresh_all_monitor_sizes<C: Connection>(&mut self, conn: &C, root_window: Window) {
+    pub fn refresh_all_monitor_sizes<C: Connection>(
+        &mut self,
+        conn: &C,
+        root_window: Window,
+    ) -> Result<(), ConfigError> {
+        let connected = util::get_monitors(conn, root_window)?;
         for monitor in &mut self.monitors {
-            monitor.refresh_size(conn, root_window);
+            monitor.refresh_size(&connected);
         }
+        Ok(())
     }
 }
 
@@ -55,8 +748,17 @@ impl fmt::Display for Config {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "alpha: {}\nline_thickness: {}\nmonitors: {:#?}\nconfigs: {:#?}",
-            self.alpha, self.line_thickness, self.monitors, self.monitor_configs
+            "alpha: {}\ntheme: {:#?}\nactivation_modifiers: {:?}\ndrop_button: {}\nzone_labels: {:?}\nrestore_size: {}\nmonitors: {:#?}\nconfigs: {:#?}\nprofiles: {:#?}\nrules: {:#?}",
+            self.alpha,
+            self.theme,
+            self.activation_modifiers,
+            self.drop_button,
+            self.zone_labels,
+            self.restore_size,
+            self.monitors,
+            self.monitor_configs,
+            self.profiles,
+            self.rules
         )
     }
 }
@@ -64,7 +766,14 @@ impl fmt::Display for Config {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Monitor {
     pub name: String,
-    pub config: Option<MonitorConfig>,
+    /// Identity of the physical monitor, preferred over `name` when matching outputs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edid: Option<Edid>,
+    /// Name of the assigned entry in [`Config::monitor_configs`].
+    pub config: Option<String>,
+    /// Assignments that replace `config` while one of these desktops is the current one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub desktops: Vec<DesktopAssignment>,
     pub x: i16,
     pub y: i16,
     pub width: u16,
@@ -83,57 +792,419 @@ impl fmt::Display for Monitor {
 
 impl Monitor {
     pub fn coords_inside(&self, x: i16, y: i16) -> bool {
-        let val = x >= self.x
+        x >= self.x
             && x <= self.x + self.width as i16
             && y >= self.y
-            && y <= self.y + self.height as i16;
-        val
+            && y <= self.y + self.height as i16
+    }
+
+    pub fn area(&self) -> Area {
+        Area {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+        }
     }
 
     pub fn to_local_space(&self, x: i16, y: i16) -> (i16, i16) {
         (x - self.x, y - self.y)
     }
 
-    pub fn refresh_global_pos<C: Connection>(&mut self, conn: &C, root_window: Window) {
-        let monitors = util::get_monitors(conn, root_window).unwrap();
-        if let Some(this) = monitors.into_iter().find(|x| x.name == self.name) {
+    /// True if `mc_name` is assigned to the monitor, on all or on some desktops.
+    pub fn uses(&self, mc_name: &str) -> bool {
+        self.config.as_deref() == Some(mc_name) || self.desktops.iter().any(|a| a.config == mc_name)
+    }
+
+    /// The config for the desktop at `index` called `name`: the desktop specific one if
+    /// there is one, [`Monitor::config`] otherwise.
+    pub fn config_for_desktop(&self, index: u32, name: Option<&str>) -> Option<&str> {
+        self.desktops
+            .iter()
+            .find(|assignment| -> bool { assignment.desktop.matches(index, name) })
+            .map(|assignment| assignment.config.as_str())
+            .or(self.config.as_deref())
+    }
+
+    /// The entry of `monitors` that is the same physical monitor, matched by EDID first
+    /// and by output name second.
+    pub fn find_in<'a>(&self, monitors: &'a [Monitor]) -> Option<&'a Monitor> {
+        self.edid
+            .as_ref()
+            .and_then(|edid| monitors.iter().find(|m| m.edid.as_ref() == Some(edid)))
+            .or_else(|| monitors.iter().find(|m| m.name == self.name))
+    }
+
+    /// Forgets the EDID identity of monitors that share it with another one of `connected`,
+    /// identical models often report no serial. Those are told apart by output name instead.
+    pub fn drop_shared_edids(connected: &mut [Monitor]) {
+        let shared: Vec<bool> = connected
+            .iter()
+            .map(|monitor| -> bool {
+                monitor.edid.is_some()
+                    && connected
+                        .iter()
+                        .filter(|other| other.edid == monitor.edid)
+                        .count()
+                        > 1
+            })
+            .collect();
+        for (monitor, shared) in connected.iter_mut().zip(shared) {
+            if shared {
+                monitor.edid = None;
+            }
+        }
+    }
+
+    /// How profiles refer to the monitor: its EDID identity if known, the output name otherwise.
+    pub fn profile_key(&self) -> String {
+        match &self.edid {
+            Some(edid) => edid.to_string(),
+            None => self.name.clone(),
+        }
+    }
+
+    /// True if `key` is the output name or the EDID identity of the monitor.
+    pub fn is_known_as(&self, key: &str) -> bool {
+        self.name == key
+            || self
+                .edid
+                .as_ref()
+                .is_some_and(|edid| edid.to_string() == key)
+    }
+
+    /// Takes the position from the matching monitor of the `connected` ones.
+    pub fn refresh_global_pos(&mut self, connected: &[Monitor]) {
+        if let Some(this) = self.find_in(connected) {
             self.x = this.x;
             self.y = this.y;
+            self.edid = self.edid.take().or_else(|| this.edid.clone());
         }
     }
 
-    pub fn refresh_size<C: Connection>(&mut self, conn: &C, root_window: Window) {
-        let monitors = util::get_monitors(conn, root_window).unwrap();
-        if let Some(this) = monitors.into_iter().find(|x| x.name == self.name) {
+    /// Takes the size from the matching monitor of the `connected` ones.
+    pub fn refresh_size(&mut self, connected: &[Monitor]) {
+        if let Some(this) = self.find_in(connected) {
             self.width = this.width;
             self.height = this.height;
+            self.edid = self.edid.take().or_else(|| this.edid.clone());
+        }
+    }
+}
+
+/// An EWMH desktop, by index as in `_NET_CURRENT_DESKTOP` (starting at 0) or by name as in
+/// `_NET_DESKTOP_NAMES`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Desktop {
+    Index(u32),
+    Name(String),
+}
+
+impl Desktop {
+    pub fn matches(&self, index: u32, name: Option<&str>) -> bool {
+        match self {
+            Desktop::Index(i) => *i == index,
+            Desktop::Name(n) => Some(n.as_str()) == name,
         }
     }
 }
 
+impl fmt::Display for Desktop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Desktop::Index(index) => write!(f, "{}", index),
+            Desktop::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl FromStr for Desktop {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("desktop must be an index or a name".to_string());
+        }
+        Ok(s.parse()
+            .map(Desktop::Index)
+            .unwrap_or_else(|_| Desktop::Name(s.to_string())))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DesktopAssignment {
+    pub desktop: Desktop,
+    /// Name of the assigned entry in [`Config::monitor_configs`].
+    pub config: String,
+}
+
+/// Named monitor to config assignments for one set of connected outputs.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    /// Outputs that have to be connected, no more and no less. Each one is either an output
+    /// name or an EDID identity, see [`Monitor::profile_key`].
+    pub outputs: Vec<String>,
+    /// Output name or EDID identity to config name.
+    pub assignments: BTreeMap<String, String>,
+}
+
+impl Profile {
+    /// A profile for the `connected` monitors with their current assignments.
+    pub fn from_monitors(name: &str, connected: &[Monitor]) -> Profile {
+        let mut outputs: Vec<String> = connected.iter().map(Monitor::profile_key).collect();
+        outputs.sort();
+        Profile {
+            name: name.to_string(),
+            outputs,
+            assignments: connected
+                .iter()
+                .filter_map(|m| Some((m.profile_key(), m.config.clone()?)))
+                .collect(),
+        }
+    }
+
+    /// True if the connected outputs are exactly the ones of this profile.
+    pub fn matches(&self, detected: &[Monitor]) -> bool {
+        detected.len() == self.outputs.len()
+            && detected.iter().all(|monitor| -> bool {
+                self.outputs
+                    .iter()
+                    .any(|output| monitor.is_known_as(output))
+            })
+    }
+
+    /// Config the profile assigns to `monitor`, looked up by EDID identity first.
+    pub fn assignment(&self, monitor: &Monitor) -> Option<&str> {
+        self.assignments
+            .get(&monitor.profile_key())
+            .or_else(|| self.assignments.get(&monitor.name))
+            .map(String::as_str)
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} [{}]", self.name, self.outputs.join(", "))?;
+        for (monitor, mc_name) in &self.assignments {
+            write!(f, "\n  {} -> {}", monitor, mc_name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Snaps windows into a zone when they are first mapped. Every matcher that is set has to
+/// match.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    /// Instance part of `WM_CLASS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// Class part of `WM_CLASS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    /// Regex the `_NET_WM_NAME` of the window has to match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_type: Option<WindowType>,
+    /// Name of the entry in [`Config::monitor_configs`] the zone is in.
+    pub config: String,
+    pub zone: String,
+}
+
+impl Rule {
+    pub fn is_catch_all(&self) -> bool {
+        self.instance.is_none()
+            && self.class.is_none()
+            && self.title.is_none()
+            && self.window_type.is_none()
+    }
+
+    pub fn title_regex(&self) -> Result<Option<Regex>, ConfigError> {
+        self.title
+            .as_deref()
+            .map(|pattern| -> Result<Regex, ConfigError> {
+                Regex::new(pattern).map_err(|source| ConfigError::InvalidPattern {
+                    pattern: pattern.to_string(),
+                    source,
+                })
+            })
+            .transpose()
+    }
+
+    /// `title` is the compiled [`Rule::title`], see [`RuleSet`].
+    fn matches(&self, info: &WindowInfo, title: Option<&Regex>) -> bool {
+        !self.is_catch_all()
+            && self.instance.as_ref().is_none_or(|i| *i == info.instance)
+            && self.class.as_ref().is_none_or(|c| *c == info.class)
+            && self.window_type.is_none_or(|t| Some(t) == info.window_type)
+            && title.is_none_or(|re| re.is_match(&info.title))
+    }
+}
+
+/// The rules of a config with their title patterns compiled, built once per loaded config
+/// instead of for every mapped window. Rules with an invalid pattern never match,
+/// [`Config::validate`] reports them.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<(Rule, Option<Regex>)>,
+}
+
+impl RuleSet {
+    pub fn new(rules: &[Rule]) -> Self {
+        let rules = rules
+            .iter()
+            .filter_map(|rule| -> Option<(Rule, Option<Regex>)> {
+                Some((rule.clone(), rule.title_regex().ok()?))
+            })
+            .collect();
+        RuleSet { rules }
+    }
+
+    /// The first rule that applies to a newly mapped window.
+    pub fn matching(&self, info: &WindowInfo) -> Option<&Rule> {
+        self.rules
+            .iter()
+            .find(|(rule, title)| -> bool { rule.matches(info, title.as_ref()) })
+            .map(|(rule, _)| rule)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {}/{}", self.name, self.config, self.zone)?;
+        if let Some(instance) = &self.instance {
+            write!(f, "\n  instance: {}", instance)?;
+        }
+        if let Some(class) = &self.class {
+            write!(f, "\n  class: {}", class)?;
+        }
+        if let Some(title) = &self.title {
+            write!(f, "\n  title: {}", title)?;
+        }
+        if let Some(window_type) = self.window_type {
+            write!(f, "\n  window_type: {}", window_type)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MonitorConfig {
     pub name: String,
     pub zones: Vec<Zone>,
+    /// Overrides for parts of the global [`Config::theme`] on monitors using this config.
+    #[serde(default, skip_serializing_if = "ThemeOverride::is_empty")]
+    pub theme: ThemeOverride,
+    /// Position and size zones relative to the monitor's work area, the part not covered
+    /// by panels and docks, instead of the whole monitor.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub work_area: bool,
 }
 
 impl MonitorConfig {
-    pub fn add_zone(&mut self, zone: Zone) {
+    fn validate(&self, assigned: &[&Monitor], diagnostics: &mut Vec<Diagnostic>) {
+        for (i, zone) in self.zones.iter().enumerate() {
+            let location = format!("config {}, zone {}", self.name, zone.name);
+            if self.zones[..i].iter().any(|other| other.name == zone.name) {
+                diagnostics.push(Diagnostic::error(
+                    &location,
+                    "zone name is used more than once",
+                ));
+            }
+
+            // relative zones can only be checked against the monitors they end up on
+            if assigned.is_empty() {
+                if let (Length::Px(width), Length::Px(height)) = (zone.width, zone.height)
+                    && (width <= 0 || height <= 0)
+                {
+                    diagnostics.push(Diagnostic::error(
+                        &location,
+                        format!("size {}x{} is not positive", width, height),
+                    ));
+                }
+                continue;
+            }
+
+            for monitor in assigned {
+                let rz = zone.resolve(monitor.width, monitor.height);
+                if rz.width <= 0 || rz.height <= 0 {
+                    diagnostics.push(Diagnostic::error(
+                        &location,
+                        format!(
+                            "size {}x{} on monitor {} is not positive",
+                            rz.width, rz.height, monitor.name
+                        ),
+                    ));
+                } else if rz.x < 0
+                    || rz.y < 0
+                    || rz.x as i32 + rz.width as i32 > monitor.width as i32
+                    || rz.y as i32 + rz.height as i32 > monitor.height as i32
+                {
+                    diagnostics.push(Diagnostic::warning(
+                        &location,
+                        format!(
+                            "extends past monitor {} ({}x{})",
+                            monitor.name, monitor.width, monitor.height
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    pub fn add_zone(&mut self, zone: Zone) -> Result<(), ConfigError> {
+        if self.get_zone(&zone.name).is_some() {
+            return Err(ConfigError::ZoneExists {
+                config: self.name.clone(),
+                zone: zone.name,
+            });
+        }
         self.zones.push(zone);
+        Ok(())
     }
 
-    pub fn remove_zone(&mut self, zone_name: &str) {
-        if let Some(index) = self
+    pub fn remove_zone(&mut self, zone_name: &str) -> Result<Zone, ConfigError> {
+        let index = self
             .zones
             .iter()
             .position(|zone| -> bool { zone.name == zone_name })
-        {
-            self.zones.remove(index);
-        } else {
-            println!(
-                "Could not find Zone with id {} assigned to config {}",
-                zone_name, self.name
-            )
+            .ok_or_else(|| self.missing_zone(zone_name))?;
+        Ok(self.zones.remove(index))
+    }
+
+    pub fn get_zone(&self, zone_name: &str) -> Option<&Zone> {
+        self.zones
+            .iter()
+            .find(|zone| -> bool { zone.name == zone_name })
+    }
+
+    pub fn get_zone_mut(&mut self, zone_name: &str) -> Result<&mut Zone, ConfigError> {
+        let missing = self.missing_zone(zone_name);
+        self.zones
+            .iter_mut()
+            .find(|zone| -> bool { zone.name == zone_name })
+            .ok_or(missing)
+    }
+
+    pub fn rename_zone(&mut self, zone_name: &str, new_name: &str) -> Result<(), ConfigError> {
+        if zone_name != new_name && self.get_zone(new_name).is_some() {
+            return Err(ConfigError::ZoneExists {
+                config: self.name.clone(),
+                zone: new_name.to_string(),
+            });
+        }
+        self.get_zone_mut(zone_name)?.name = new_name.to_string();
+        Ok(())
+    }
+
+    fn missing_zone(&self, zone_name: &str) -> ConfigError {
+        ConfigError::MissingZone {
+            config: self.name.clone(),
+            zone: zone_name.to_string(),
         }
     }
 }
@@ -144,13 +1215,137 @@ impl fmt::Display for MonitorConfig {
     }
 }
 
+/// A zone coordinate or extent, either in pixels or relative to the monitor size.
+///
+/// Parses from `"40"` / `"40px"` (pixels), `"33%"` (percent) and `"0.33"` (fraction).
+/// In the config file pixels are stored as integers, fractions as floats and percentages
+/// as strings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Length {
+    Px(i16),
+    Percent(f32),
+    Fraction(f32),
+}
+
+impl Length {
+    /// Resolves the length to pixels against the monitor extent along the same axis.
+    pub fn resolve(self, total: u16) -> i16 {
+        match self {
+            Length::Px(px) => px,
+            Length::Percent(p) => (total as f32 * p / 100.0).round() as i16,
+            Length::Fraction(f) => (total as f32 * f).round() as i16,
+        }
+    }
+
+    /// The same length as a fraction of `total`, so it scales with whatever it is resolved
+    /// against.
+    pub fn to_fraction(self, total: u16) -> Length {
+        match self {
+            Length::Px(px) => Length::Fraction(px as f32 / total as f32),
+            Length::Percent(p) => Length::Fraction(p / 100.0),
+            Length::Fraction(f) => Length::Fraction(f),
+        }
+    }
+
+    /// Adds `by` to the length if both use the same unit and the pixel sum fits.
+    pub fn offset(self, by: Length) -> Result<Length, OffsetError> {
+        match (self, by) {
+            (Length::Px(a), Length::Px(b)) => a
+                .checked_add(b)
+                .map(Length::Px)
+                .ok_or(OffsetError::Overflow),
+            (Length::Percent(a), Length::Percent(b)) => Ok(Length::Percent(a + b)),
+            (Length::Fraction(a), Length::Fraction(b)) => Ok(Length::Fraction(a + b)),
+            // moving by nothing is fine for every unit
+            (length, Length::Px(0)) => Ok(length),
+            _ => Err(OffsetError::MixedUnits),
+        }
+    }
+}
+
+/// Why [`Length::offset`] failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffsetError {
+    MixedUnits,
+    Overflow,
+}
+
+impl From<i16> for Length {
+    fn from(px: i16) -> Self {
+        Length::Px(px)
+    }
+}
+
+impl FromStr for Length {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let length = if let Some(percent) = s.strip_suffix('%') {
+            percent.trim().parse().ok().map(Length::Percent)
+        } else if let Some(px) = s.strip_suffix("px") {
+            px.trim().parse().ok().map(Length::Px)
+        } else if s.contains('.') {
+            s.parse().ok().map(Length::Fraction)
+        } else {
+            s.parse().ok().map(Length::Px)
+        };
+        length.ok_or_else(|| {
+            format!(
+                "invalid length {:?}, expected e.g. 40, 40px, 33% or 0.33",
+                s
+            )
+        })
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Length::Px(px) => write!(f, "{}", px),
+            Length::Percent(p) => write!(f, "{}%", p),
+            Length::Fraction(frac) => write!(f, "{:?}", frac),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawLength {
+    Px(i16),
+    Fraction(f32),
+    Str(String),
+}
+
+impl Serialize for Length {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Length::Px(px) => RawLength::Px(px),
+            Length::Fraction(f) => RawLength::Fraction(f),
+            Length::Percent(_) => RawLength::Str(self.to_string()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Length {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match RawLength::deserialize(deserializer)? {
+            RawLength::Px(px) => Ok(Length::Px(px)),
+            RawLength::Fraction(f) => Ok(Length::Fraction(f)),
+            RawLength::Str(s) => s.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// A zone as stored in a [`MonitorConfig`], relative to the monitor's top left corner.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Zone {
     pub name: String,
-    pub x: i16,
-    pub y: i16,
-    pub width: i16,
-    pub height: i16,
+    pub x: Length,
+    pub y: Length,
+    pub width: Length,
+    pub height: Length,
 }
 
 impl fmt::Display for Zone {
@@ -164,15 +1359,52 @@ impl fmt::Display for Zone {
 }
 
 impl Zone {
+    /// Resolves the zone to pixels on a monitor of the given size.
+    pub fn resolve(&self, width: u16, height: u16) -> ResolvedZone {
+        ResolvedZone {
+            name: self.name.clone(),
+            x: self.x.resolve(width),
+            y: self.y.resolve(height),
+            width: self.width.resolve(width),
+            height: self.height.resolve(height),
+        }
+    }
+
+    /// The zone with every length as a fraction of a `width`x`height` monitor.
+    pub fn to_fractions(&self, width: u16, height: u16) -> Zone {
+        Zone {
+            name: self.name.clone(),
+            x: self.x.to_fraction(width),
+            y: self.y.to_fraction(height),
+            width: self.width.to_fraction(width),
+            height: self.height.to_fraction(height),
+        }
+    }
+}
+
+/// A zone in absolute pixels, as used by the overlay.
+#[derive(Debug, Clone)]
+pub struct ResolvedZone {
+    pub name: String,
+    pub x: i16,
+    pub y: i16,
+    pub width: i16,
+    pub height: i16,
+}
+
+impl ResolvedZone {
+    pub fn translate(mut self, dx: i16, dy: i16) -> Self {
+        self.x += dx;
+        self.y += dy;
+        self
+    }
+
     pub fn get_center_point(&self) -> (i16, i16) {
-        (
-            (self.width / 2 + self.x) as i16,
-            (self.height / 2 + self.y) as i16,
-        )
+        (self.width / 2 + self.x, self.height / 2 + self.y)
     }
 
     pub fn is_inside(&self, x: i16, y: i16) -> bool {
-        self.name != ""
+        !self.name.is_empty()
             && x >= self.x
             && x <= self.x + self.width
             && y >= self.y
@@ -191,8 +1423,12 @@ impl Zone {
     }
 }
 
-pub fn init_cfg_file<C: Connection>(conn: &C, root: u32) {
-    let mut monitors = util::get_monitors(conn, root).unwrap();
+pub fn init_cfg_file<C: Connection>(
+    conn: &C,
+    root: u32,
+    path: &Path,
+) -> Result<Config, ConfigError> {
+    let mut monitors = util::get_monitors(conn, root)?;
     let mut monitor_configs = vec![];
 
     for monitor in &mut monitors {
@@ -200,43 +1436,261 @@ pub fn init_cfg_file<C: Connection>(conn: &C, root: u32) {
         let monitor_config = MonitorConfig {
             name: monitor.name.clone(),
             zones,
+            theme: ThemeOverride::default(),
+            work_area: false,
         };
-        monitor_configs.push(monitor_config.clone());
-        monitor.config = Some(monitor_config);
+        monitor_configs.push(monitor_config);
+        monitor.config = Some(monitor.name.clone());
     }
 
     let config = Config {
+        version: CONFIG_VERSION,
         monitors,
         monitor_configs,
         alpha: 0.5,
-        line_thickness: 3,
+        theme: Theme::default(),
+        activation_modifiers: vec![Modifier::Control],
+        drop_button: DEFAULT_DROP_BUTTON,
+        zone_labels: ZoneLabels::default(),
+        profiles: vec![],
+        restore_size: true,
+        rules: vec![],
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| ConfigError::io(dir, e))?;
+    }
+    save_cfg_file(path, &config)?;
+    Ok(config)
+}
+
+/// Loads the config, upgrading documents written by older versions on the way.
+pub fn load_cfg_file(path: &Path) -> Result<Config, ConfigError> {
+    let data = read(path).map_err(|e| ConfigError::io(path, e))?;
+    let mut doc: Value = serde_json::from_slice(&data).map_err(|e| ConfigError::parse(path, e))?;
+    migrate(&mut doc).map_err(|version| ConfigError::UnsupportedVersion {
+        path: path.to_path_buf(),
+        version,
+    })?;
+    serde_json::from_value(doc).map_err(|e| ConfigError::parse(path, e))
+}
+
+type Migration = fn(&mut Value);
+
+/// `MIGRATIONS[n]` upgrades a document from version `n` to `n + 1`.
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+    migrate_v8_to_v9,
+    migrate_v9_to_v10,
+    migrate_v10_to_v11,
+    migrate_v11_to_v12,
+];
+
+/// Upgrades a raw config document step by step to [`CONFIG_VERSION`].
+/// Documents without a `version` field are treated as version 0.
+/// Returns the document version as error if it is newer than this build understands.
+pub fn migrate(doc: &mut Value) -> Result<(), u64> {
+    let version = doc.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > CONFIG_VERSION as u64 {
+        return Err(version);
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(doc);
+        if let Some(obj) = doc.as_object_mut() {
+            obj.insert("version".to_string(), Value::from(from + 1));
+        }
+    }
+    Ok(())
+}
+
+/// v0 is the unversioned format, v1 only introduces the `version` field itself.
+fn migrate_v0_to_v1(_doc: &mut Value) {}
+
+/// v2 allows relative zone geometry. Pixel values are still valid, so nothing to convert.
+fn migrate_v1_to_v2(_doc: &mut Value) {}
+
+/// v3 stores only the config name on a monitor instead of a full copy of the config.
+/// Copies without a matching entry in `monitor_configs` are moved there so no zones get lost.
+fn migrate_v2_to_v3(doc: &mut Value) {
+    let Some(obj) = doc.as_object_mut() else {
+        return;
+    };
+
+    let mut orphaned = Vec::new();
+    if let Some(Value::Array(monitors)) = obj.get_mut("monitors") {
+        for monitor in monitors {
+            let Some(config) = monitor.get_mut("config") else {
+                continue;
+            };
+            if let Some(name) = config.get("name").cloned() {
+                orphaned.push(config.take());
+                *config = name;
+            }
+        }
+    }
+
+    if let Some(Value::Array(configs)) = obj.get_mut("monitor_configs") {
+        for config in orphaned {
+            if !configs.iter().any(|c| c.get("name") == config.get("name")) {
+                configs.push(config);
+            }
+        }
+    }
+}
+
+/// v4 makes the activation modifiers and the drop button configurable.
+/// Older builds always used Ctrl and the left mouse button.
+fn migrate_v3_to_v4(doc: &mut Value) {
+    if let Some(obj) = doc.as_object_mut() {
+        obj.insert(
+            "activation_modifiers".to_string(),
+            serde_json::json!([Modifier::Control]),
+        );
+        obj.insert("drop_button".to_string(), Value::from(DEFAULT_DROP_BUTTON));
+    }
+}
+
+/// v5 replaces `line_thickness` with a `theme` section. The defaults reproduce the old
+/// white on black look.
+fn migrate_v4_to_v5(doc: &mut Value) {
+    if let Some(obj) = doc.as_object_mut() {
+        let mut theme = Theme::default();
+        if let Some(thickness) = obj
+            .remove("line_thickness")
+            .and_then(|t| t.as_u64())
+            .and_then(|t| u16::try_from(t).ok())
+        {
+            theme.line_thickness = thickness;
+        }
+        obj.insert(
+            "theme".to_string(),
+            serde_json::to_value(theme).expect("Theme is always serializable"),
+        );
+    }
+}
+
+/// v6 adds zone labels and their colours to the theme. Older builds drew no labels.
+fn migrate_v5_to_v6(doc: &mut Value) {
+    let Some(obj) = doc.as_object_mut() else {
+        return;
     };
+    obj.insert(
+        "zone_labels".to_string(),
+        serde_json::json!(ZoneLabels::None),
+    );
+    if let Some(Value::Object(theme)) = obj.get_mut("theme") {
+        let defaults = Theme::default();
+        theme.insert("label".to_string(), serde_json::json!(defaults.label));
+        theme.insert(
+            "active_label".to_string(),
+            serde_json::json!(defaults.active_label),
+        );
+    }
+}
+
+/// v7 adds monitor profiles.
+fn migrate_v6_to_v7(doc: &mut Value) {
+    if let Some(obj) = doc.as_object_mut() {
+        obj.insert("profiles".to_string(), serde_json::json!([]));
+    }
+}
+
+/// v8 can store the EDID identity on monitors. Monitors without one are still matched by
+/// output name, so nothing to convert.
+fn migrate_v7_to_v8(_doc: &mut Value) {}
+
+/// v9 lets configs opt into zones relative to the work area. Without the flag zones stay
+/// relative to the whole monitor, so nothing to convert.
+fn migrate_v8_to_v9(_doc: &mut Value) {}
+
+/// v10 adds desktop specific assignments to monitors. Without them the monitor's config
+/// is used on every desktop as before, so nothing to convert.
+fn migrate_v9_to_v10(_doc: &mut Value) {}
+
+/// v11 adds restoring the size of windows dragged out of their zone. Older configs keep
+/// the zone's size as before.
+fn migrate_v10_to_v11(doc: &mut Value) {
+    if let Some(obj) = doc.as_object_mut() {
+        obj.insert("restore_size".to_string(), Value::Bool(false));
+    }
+}
+
+/// v12 adds application rules.
+fn migrate_v11_to_v12(doc: &mut Value) {
+    if let Some(obj) = doc.as_object_mut() {
+        obj.insert("rules".to_string(), serde_json::json!([]));
+    }
+}
+
+/// Writes the config atomically: the data goes to a temp file next to `path`, gets synced
+/// and is then renamed over the original. The previous file is rotated into the backups.
+pub fn save_cfg_file(path: &Path, config: &Config) -> Result<(), ConfigError> {
+    let data = serde_json::to_vec(config).map_err(|e| ConfigError::io(path, e.into()))?;
+    let tmp_path = with_suffix(path, "tmp");
+    let mut file = File::create(&tmp_path).map_err(|e| ConfigError::io(&tmp_path, e))?;
+    file.write_all(&data)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| ConfigError::io(&tmp_path, e))?;
+
+    rotate_backups(path)?;
+    fs::rename(&tmp_path, path).map_err(|e| ConfigError::io(path, e))?;
+    sync_parent_dir(path);
+    Ok(())
+}
+
+/// Path of the `n`th backup of `path`, `1` being the most recent one.
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    with_suffix(path, &n.to_string())
+}
+
+/// Replaces the config with its `n`th backup. The current config becomes backup `1`, so a
+/// restore can itself be undone.
+pub fn restore_backup(path: &Path, n: usize) -> Result<Config, ConfigError> {
+    let backup = backup_path(path, n);
+    if !backup.exists() {
+        return Err(ConfigError::MissingBackup(backup));
+    }
+    let config = load_cfg_file(&backup)?;
+    save_cfg_file(path, &config)?;
+    Ok(config)
+}
+
+fn rotate_backups(path: &Path) -> Result<(), ConfigError> {
+    if !path.exists() {
+        return Ok(());
+    }
+    for n in (1..BACKUP_COUNT).rev() {
+        let from = backup_path(path, n);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, n + 1)).map_err(|e| ConfigError::io(&from, e))?;
+        }
+    }
+    // copy instead of rename so the config never disappears, even if we crash right here
+    let latest = backup_path(path, 1);
+    fs::copy(path, &latest).map_err(|e| ConfigError::io(&latest, e))?;
+    Ok(())
+}
+
+pub(crate) fn sync_parent_dir(path: &Path) {
+    // makes the rename durable, failing here is not worth aborting the save for
+    if let Some(dir) = path.parent()
+        && let Ok(dir) = File::open(dir)
+    {
+        _ = dir.sync_all();
+    }
+}
 
-    let path = get_config_path();
-    let dir = path.parent().unwrap_or(Path::new("/"));
-    fs::create_dir_all(&dir).expect(&format!("Failed to create dir at {:#?}", dir));
-    let mut cfg_file = File::create(&path).expect(&format!(
-        "Failed to create fresh config file at {:#?}.",
-        path
-    ));
-    let data = serde_json::to_vec(&config).unwrap();
-    _ = cfg_file
-        .write(&data)
-        .expect(&format!("Failed to write config file at {:#?}", path));
-}
-
-pub fn load_cfg_file() -> Config {
-    let path = get_config_path();
-    let data = read(&path).expect(&format!("Failed to read config file at {:#?}", path));
-    serde_json::from_slice(&data).unwrap()
-}
-
-pub fn save_cfg_file(config: &Config) {
-    let path = get_config_path();
-    let data = serde_json::to_vec(config).unwrap();
-    let mut file =
-        File::create(&path).expect(&format!("Failed to open config file at {:#?}", path));
-    _ = file
-        .write(&data)
-        .expect(&format!("Failed to write config file at {:#?}", path));
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
 }
@@ -0,0 +1,56 @@
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+/// Identity of a physical monitor from its EDID block. Unlike the RandR output name it
+/// stays the same whichever port or dock the monitor is plugged into.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Edid {
+    /// Three letter PNP id, e.g. `DEL`.
+    pub manufacturer: String,
+    pub model: u16,
+    /// Serial number, 0 if the monitor doesn't report one.
+    pub serial: u32,
+}
+
+impl Edid {
+    /// Parses the vendor block of a raw EDID. Returns `None` for anything that doesn't start
+    /// with the EDID header.
+    pub fn parse(data: &[u8]) -> Option<Edid> {
+        if data.len() < 16 || data[..8] != HEADER {
+            return None;
+        }
+
+        // three 5 bit letters, 1 is 'A'
+        let id = u16::from_be_bytes([data[8], data[9]]);
+        let manufacturer = [10, 5, 0]
+            .iter()
+            .map(|shift| {
+                let letter = ((id >> shift) & 0x1f) as u8;
+                if (1..=26).contains(&letter) {
+                    (b'A' + letter - 1) as char
+                } else {
+                    '?'
+                }
+            })
+            .collect();
+
+        Some(Edid {
+            manufacturer,
+            model: u16::from_le_bytes([data[10], data[11]]),
+            serial: u32::from_le_bytes([data[12], data[13], data[14], data[15]]),
+        })
+    }
+}
+
+impl fmt::Display for Edid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{:04X}-{:08X}",
+            self.manufacturer, self.model, self.serial
+        )
+    }
+}
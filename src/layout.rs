@@ -0,0 +1,330 @@
+use core::fmt;
+use std::str::FromStr;
+
+use crate::config::{Length, Zone};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutError {
+    NoWeights,
+    ZeroWeights,
+    SpanOutOfBounds(CellSpan),
+    SpanOverlap(CellSpan, CellSpan),
+    InvalidCount(Template, usize),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LayoutError::NoWeights => write!(f, "rows and columns need at least one weight"),
+            LayoutError::ZeroWeights => write!(f, "row and column weights must not all be 0"),
+            LayoutError::SpanOutOfBounds(span) => {
+                write!(f, "merged cells {} exceed the grid", span)
+            }
+            LayoutError::SpanOverlap(a, b) => write!(f, "merged cells {} and {} overlap", a, b),
+            LayoutError::InvalidCount(template, count) => {
+                write!(
+                    f,
+                    "template {} does not support a count of {}",
+                    template, count
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// A rectangular block of grid cells merged into a single zone.
+/// Rows and columns are 1-based and inclusive, written as `r1c1:r2c3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellSpan {
+    pub first_row: usize,
+    pub first_col: usize,
+    pub last_row: usize,
+    pub last_col: usize,
+}
+
+impl CellSpan {
+    fn contains(&self, row: usize, col: usize) -> bool {
+        (self.first_row..=self.last_row).contains(&row)
+            && (self.first_col..=self.last_col).contains(&col)
+    }
+
+    fn overlaps(&self, other: &CellSpan) -> bool {
+        self.first_row <= other.last_row
+            && other.first_row <= self.last_row
+            && self.first_col <= other.last_col
+            && other.first_col <= self.last_col
+    }
+}
+
+impl fmt::Display for CellSpan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            cell_name(self.first_row, self.first_col),
+            cell_name(self.last_row, self.last_col)
+        )
+    }
+}
+
+impl FromStr for CellSpan {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid cell span {:?}, expected e.g. r1c1:r2c3", s);
+        let (first, last) = s.split_once(':').ok_or_else(invalid)?;
+        let (first_row, first_col) = parse_cell(first).ok_or_else(invalid)?;
+        let (last_row, last_col) = parse_cell(last).ok_or_else(invalid)?;
+        if first_row == 0 || first_col == 0 || first_row > last_row || first_col > last_col {
+            return Err(invalid());
+        }
+        Ok(CellSpan {
+            first_row,
+            first_col,
+            last_row,
+            last_col,
+        })
+    }
+}
+
+fn parse_cell(s: &str) -> Option<(usize, usize)> {
+    let (row, col) = s.trim().strip_prefix('r')?.split_once('c')?;
+    Some((row.parse().ok()?, col.parse().ok()?))
+}
+
+/// Name of the zone generated for a single grid cell, e.g. `r1c2`.
+pub fn cell_name(row: usize, col: usize) -> String {
+    format!("r{}c{}", row, col)
+}
+
+/// Splits `total` pixels by `weights`, returning the `weights.len() + 1` edges.
+/// Edges are rounded from the cumulative weight so the parts always add up to `total`.
+fn split_edges(total: u16, weights: &[u32]) -> Result<Vec<i16>, LayoutError> {
+    if weights.is_empty() {
+        return Err(LayoutError::NoWeights);
+    }
+    let sum: u64 = weights.iter().map(|w| *w as u64).sum();
+    if sum == 0 {
+        return Err(LayoutError::ZeroWeights);
+    }
+
+    let mut edges = vec![0];
+    let mut acc = 0_u64;
+    for weight in weights {
+        acc += *weight as u64;
+        edges.push(((total as u64 * acc + sum / 2) / sum) as i16);
+    }
+    Ok(edges)
+}
+
+/// Generates the zones of a grid on a `width`x`height` monitor.
+///
+/// Every cell becomes a zone named after its position (see [`cell_name`]), except cells
+/// covered by one of `merges`, which become a single zone named after the span.
+/// Zones are ordered row by row with merged zones at the position of their first cell.
+pub fn grid(
+    width: u16,
+    height: u16,
+    cols: &[u32],
+    rows: &[u32],
+    merges: &[CellSpan],
+) -> Result<Vec<Zone>, LayoutError> {
+    let col_edges = split_edges(width, cols)?;
+    let row_edges = split_edges(height, rows)?;
+
+    for (i, span) in merges.iter().enumerate() {
+        if span.last_row > rows.len() || span.last_col > cols.len() {
+            return Err(LayoutError::SpanOutOfBounds(*span));
+        }
+        if let Some(other) = merges[..i].iter().find(|other| other.overlaps(span)) {
+            return Err(LayoutError::SpanOverlap(*other, *span));
+        }
+    }
+
+    let mut zones = Vec::new();
+    for row in 1..=rows.len() {
+        for col in 1..=cols.len() {
+            let (name, last_row, last_col) = match merges.iter().find(|m| m.contains(row, col)) {
+                Some(span) if span.first_row == row && span.first_col == col => {
+                    (span.to_string(), span.last_row, span.last_col)
+                }
+                Some(_) => continue,
+                None => (cell_name(row, col), row, col),
+            };
+            let x = col_edges[col - 1];
+            let y = row_edges[row - 1];
+            zones.push(Zone {
+                name,
+                x: Length::Px(x),
+                y: Length::Px(y),
+                width: Length::Px(col_edges[last_col] - x),
+                height: Length::Px(row_edges[last_row] - y),
+            });
+        }
+    }
+    Ok(zones)
+}
+
+/// Built-in parameterised layouts, see [`Template::expand`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    Columns,
+    Rows,
+    PriorityGrid,
+    Focus,
+    Grid,
+}
+
+impl Template {
+    pub const ALL: [Template; 5] = [
+        Template::Columns,
+        Template::Rows,
+        Template::PriorityGrid,
+        Template::Focus,
+        Template::Grid,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Template::Columns => "columns",
+            Template::Rows => "rows",
+            Template::PriorityGrid => "priority-grid",
+            Template::Focus => "focus",
+            Template::Grid => "grid",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Template::Columns => "N equal columns (default 3)",
+            Template::Rows => "N equal rows (default 3)",
+            Template::PriorityGrid => {
+                "big centre zone with N stacked zones on each side (default 1)"
+            }
+            Template::Focus => "N cascading centred zones (default 1)",
+            Template::Grid => "N x N equal cells (default 2)",
+        }
+    }
+
+    fn default_count(self) -> usize {
+        match self {
+            Template::Columns | Template::Rows => 3,
+            Template::PriorityGrid | Template::Focus => 1,
+            Template::Grid => 2,
+        }
+    }
+
+    /// Generates the template's zones for a `width`x`height` monitor.
+    pub fn expand(
+        self,
+        width: u16,
+        height: u16,
+        count: Option<usize>,
+    ) -> Result<Vec<Zone>, LayoutError> {
+        let count = count.unwrap_or(self.default_count());
+        if count == 0 {
+            return Err(LayoutError::InvalidCount(self, count));
+        }
+
+        let zones = match self {
+            Template::Columns => rename(grid(width, height, &vec![1; count], &[1], &[])?, "col"),
+            Template::Rows => rename(grid(width, height, &[1], &vec![1; count], &[])?, "row"),
+            Template::Grid => grid(width, height, &vec![1; count], &vec![1; count], &[])?,
+            Template::PriorityGrid => {
+                let centre = CellSpan {
+                    first_row: 1,
+                    first_col: 2,
+                    last_row: count,
+                    last_col: 2,
+                };
+                let mut zones = grid(width, height, &[1, 2, 1], &vec![1; count], &[centre])?;
+                let mut row = 0;
+                for zone in &mut zones {
+                    zone.name = if zone.name == centre.to_string() {
+                        "main".to_string()
+                    } else if zone.name.ends_with("c1") {
+                        row += 1;
+                        numbered("left", row, count)
+                    } else {
+                        numbered("right", row, count)
+                    };
+                }
+                zones
+            }
+            Template::Focus => {
+                const OFFSET: i32 = 40;
+                let invalid = || LayoutError::InvalidCount(self, count);
+                let zone_width = (width as f32 * 0.6).round() as i32;
+                let zone_height = (height as f32 * 0.7).round() as i32;
+                let Some(cascade) = i32::try_from(count - 1)
+                    .ok()
+                    .and_then(|n| n.checked_mul(OFFSET))
+                else {
+                    return Err(invalid());
+                };
+                let x = (width as i32 - zone_width - cascade) / 2;
+                let y = (height as i32 - zone_height - cascade) / 2;
+                if x < 0 || y < 0 {
+                    return Err(invalid());
+                }
+                let px = |v: i32| -> Result<Length, LayoutError> {
+                    i16::try_from(v).map(Length::Px).map_err(|_| invalid())
+                };
+                (0..count as i32)
+                    .map(|i| -> Result<Zone, LayoutError> {
+                        Ok(Zone {
+                            name: numbered("focus", i as usize + 1, count),
+                            x: px(x + i * OFFSET)?,
+                            y: px(y + i * OFFSET)?,
+                            width: px(zone_width)?,
+                            height: px(zone_height)?,
+                        })
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+        };
+        Ok(zones)
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Template::ALL
+            .into_iter()
+            .find(|template| template.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Template::ALL.iter().map(|t| t.name()).collect();
+                format!(
+                    "unknown template {:?}, expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// `base` when there is only one zone of its kind, `base1`, `base2`, ... otherwise.
+fn numbered(base: &str, n: usize, count: usize) -> String {
+    if count == 1 {
+        base.to_string()
+    } else {
+        format!("{}{}", base, n)
+    }
+}
+
+fn rename(mut zones: Vec<Zone>, base: &str) -> Vec<Zone> {
+    for (i, zone) in zones.iter_mut().enumerate() {
+        zone.name = format!("{}{}", base, i + 1);
+    }
+    zones
+}
@@ -1,2 +1,7 @@
 pub mod config;
+pub mod edid;
+pub mod layout;
+pub mod state;
 pub mod util;
+pub mod window;
+pub mod workarea;
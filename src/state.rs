@@ -0,0 +1,210 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{self, ConfigError},
+    window::WindowInfo,
+};
+
+const STATE_DIR_NAME: &str = "lancy-zones";
+const STATE_FILE_NAME: &str = "state.json";
+
+/// Placements beyond this many are dropped, oldest first.
+pub const MAX_PLACEMENTS: usize = 200;
+
+/// Default state file location following the XDG base directory spec:
+/// `$XDG_STATE_HOME/lancy-zones/state.json` or `$HOME/.local/state/lancy-zones/state.json`.
+pub fn get_state_path() -> Option<PathBuf> {
+    let state_home = config::non_empty_var("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| {
+            config::non_empty_var("HOME").map(|home| PathBuf::from(home).join(".local/state"))
+        })?;
+    Some(state_home.join(STATE_DIR_NAME).join(STATE_FILE_NAME))
+}
+
+/// A zone by the monitor it is on, the layout it belongs to and its name, which stay the
+/// same when the layout is edited or the monitors are rearranged.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZoneRef {
+    /// [`Monitor::profile_key`](crate::config::Monitor::profile_key) of the monitor.
+    pub monitor: String,
+    /// Name of the [`MonitorConfig`](crate::config::MonitorConfig) the zone is in. Empty for
+    /// the monitor background and for zones recorded before the layout was, those follow
+    /// whatever layout the monitor shows.
+    #[serde(default)]
+    pub config: String,
+    pub name: String,
+}
+
+impl ZoneRef {
+    /// Value of the window property, monitor, layout and zone name separated by null bytes.
+    pub fn to_property(&self) -> Vec<u8> {
+        [
+            self.monitor.as_bytes(),
+            self.config.as_bytes(),
+            self.name.as_bytes(),
+        ]
+        .join(&0)
+    }
+
+    pub fn from_property(value: &[u8]) -> Option<Self> {
+        let value = std::str::from_utf8(value).ok()?;
+        let parts: Vec<&str> = value.splitn(3, '\0').collect();
+        // properties of older versions have no layout
+        let (monitor, config, name) = match parts[..] {
+            [monitor, config, name] => (monitor, config, name),
+            [monitor, name] => (monitor, "", name),
+            _ => return None,
+        };
+        Some(ZoneRef {
+            monitor: monitor.to_string(),
+            config: config.to_string(),
+            name: name.to_string(),
+        })
+    }
+}
+
+/// The zone a window was last snapped into.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Placement {
+    pub instance: String,
+    pub class: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// Regex the title has to match. Saved as the exact title, edit it to match more.
+    pub title: String,
+    pub zone: ZoneRef,
+}
+
+impl Placement {
+    /// Same application and role, whatever the title.
+    fn is_same_kind(&self, info: &WindowInfo) -> bool {
+        self.instance == info.instance && self.class == info.class && self.role == info.role
+    }
+
+    fn matches_title(&self, title: &str, patterns: &TitlePatterns) -> bool {
+        // titles saved by remember need no regex, only edited patterns do
+        self.title == exact_title(title) || patterns.is_match(&self.title, title)
+    }
+
+    fn matches(&self, info: &WindowInfo, patterns: &TitlePatterns) -> bool {
+        self.is_same_kind(info) && self.matches_title(&info.title, patterns)
+    }
+}
+
+fn exact_title(title: &str) -> String {
+    format!("^{}$", regex::escape(title))
+}
+
+/// Title patterns compiled on first use, once per loaded [`State`]. Invalid patterns never
+/// match.
+#[derive(Debug, Default)]
+struct TitlePatterns(RefCell<HashMap<String, Option<Regex>>>);
+
+impl TitlePatterns {
+    fn is_match(&self, pattern: &str, title: &str) -> bool {
+        self.0
+            .borrow_mut()
+            .entry(pattern.to_string())
+            .or_insert_with(|| Regex::new(pattern).ok())
+            .as_ref()
+            .is_some_and(|re| re.is_match(title))
+    }
+}
+
+/// Where windows were snapped, kept across sessions so they can be put back when they
+/// are mapped again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct State {
+    /// Oldest first.
+    pub placements: Vec<Placement>,
+    #[serde(skip)]
+    patterns: TitlePatterns,
+}
+
+impl State {
+    /// Records that the window described by `info` was snapped into `zone`.
+    pub fn remember(&mut self, info: &WindowInfo, zone: ZoneRef) {
+        self.forget(info);
+        self.placements.push(Placement {
+            instance: info.instance.clone(),
+            class: info.class.clone(),
+            role: info.role.clone(),
+            title: exact_title(&info.title),
+            zone,
+        });
+        if self.placements.len() > MAX_PLACEMENTS {
+            self.placements
+                .drain(..self.placements.len() - MAX_PLACEMENTS);
+        }
+    }
+
+    /// Drops the placements of the window described by `info`.
+    pub fn forget(&mut self, info: &WindowInfo) {
+        let patterns = &self.patterns;
+        self.placements
+            .retain(|placement| -> bool { !placement.matches(info, patterns) });
+    }
+
+    /// The zone for a window that is mapped: the most recent placement whose title pattern
+    /// matches or, failing that, the most recent one of the same application and role.
+    pub fn find(&self, info: &WindowInfo) -> Option<&ZoneRef> {
+        let mut same_kind = self
+            .placements
+            .iter()
+            .rev()
+            .filter(|placement| -> bool { placement.is_same_kind(info) });
+        let fallback = same_kind.clone().next();
+        same_kind
+            .find(|placement| -> bool { placement.matches_title(&info.title, &self.patterns) })
+            .or(fallback)
+            .map(|placement| &placement.zone)
+    }
+}
+
+/// Loads the state file, a missing file is an empty state.
+pub fn load_state_file(path: &Path) -> Result<State, ConfigError> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(State::default()),
+        Err(e) => return Err(io_error(path, e)),
+    };
+    serde_json::from_slice(&data).map_err(|source| ConfigError::StateParse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Writes the state atomically through a temp file next to `path`. Unlike the config no
+/// backups are kept, the state is rebuilt as windows get snapped.
+pub fn save_state_file(path: &Path, state: &State) -> Result<(), ConfigError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| io_error(dir, e))?;
+    }
+    let data = serde_json::to_vec(state).map_err(|e| io_error(path, e.into()))?;
+    let tmp_path = config::with_suffix(path, "tmp");
+    let mut file = File::create(&tmp_path).map_err(|e| io_error(&tmp_path, e))?;
+    file.write_all(&data)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| io_error(&tmp_path, e))?;
+    fs::rename(&tmp_path, path).map_err(|e| io_error(path, e))?;
+    config::sync_parent_dir(path);
+    Ok(())
+}
+
+fn io_error(path: &Path, source: io::Error) -> ConfigError {
+    ConfigError::StateIo {
+        path: path.to_path_buf(),
+        source,
+    }
+}
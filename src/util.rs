@@ -1,15 +1,17 @@
-use std::cmp::Ordering;
-use std::fmt::Display;
 use std::str;
 
-use serde::Deserialize;
-use serde::Serialize;
 use x11rb::connection::Connection;
 use x11rb::errors::ReplyOrIdError;
 use x11rb::protocol::randr;
+use x11rb::protocol::xinerama;
 use x11rb::protocol::xproto::*;
 
-use crate::config;
+use crate::{
+    config,
+    edid::Edid,
+    window::{WindowInfo, WindowType},
+    workarea::{self, Area, Strut},
+};
 
 pub fn scan_windows<C: Connection>(
     con: &C,
@@ -28,58 +30,401 @@ pub fn scan_windows<C: Connection>(
     let mut all_windows: Vec<u32> = Vec::with_capacity(cookies.len());
 
     for (win, attr, geom) in cookies {
-        if let (Ok(attr), Ok(_geom)) = (attr.reply(), geom.reply()) {
-            if !attr.override_redirect && condition(attr.map_state) {
-                let win_name = con
-                    .get_property(false, win, AtomEnum::WM_NAME, AtomEnum::STRING, 0, u32::MAX)?
-                    .reply()?
-                    .value;
-                all_windows.push(win);
-                println!("Found window: {}", str::from_utf8(&win_name).unwrap());
-            }
+        if let (Ok(attr), Ok(_geom)) = (attr.reply(), geom.reply())
+            && !attr.override_redirect
+            && condition(attr.map_state)
+        {
+            let win_name = con
+                .get_property(false, win, AtomEnum::WM_NAME, AtomEnum::STRING, 0, u32::MAX)?
+                .reply()?
+                .value;
+            all_windows.push(win);
+            println!("Found window: {}", str::from_utf8(&win_name).unwrap());
         }
     }
 
     Ok(all_windows)
 }
 
-
+/// The monitors of the screen, from the best source the server supports: RandR 1.5
+/// logical monitors, the RandR CRTC of every connected output, Xinerama screens or, failing
+/// all of these, the root window as a single monitor.
 pub fn get_monitors<C: Connection>(
     conn: &C,
     root_window: Window,
+) -> Result<Vec<config::Monitor>, ReplyOrIdError> {
+    if conn
+        .extension_information(randr::X11_EXTENSION_NAME)?
+        .is_some()
+    {
+        // RandR errors and empty results only mean the next source has to do
+        let logical = || -> Result<Vec<config::Monitor>, ReplyOrIdError> {
+            let version = randr::query_version(conn, 1, 5)?.reply()?;
+            if (version.major_version, version.minor_version) >= (1, 5) {
+                get_logical_monitors(conn, root_window)
+            } else {
+                Ok(Vec::new())
+            }
+        };
+        let monitors = logical()
+            .ok()
+            .filter(|monitors| -> bool { !monitors.is_empty() })
+            .or_else(|| get_crtc_monitors(conn, root_window).ok())
+            .filter(|monitors| -> bool { !monitors.is_empty() });
+        if let Some(mut monitors) = monitors {
+            config::Monitor::drop_shared_edids(&mut monitors);
+            return Ok(monitors);
+        }
+    }
+
+    if conn
+        .extension_information(xinerama::X11_EXTENSION_NAME)?
+        .is_some()
+        && xinerama::is_active(conn)?.reply()?.state != 0
+    {
+        let screens = xinerama::query_screens(conn)?.reply()?.screen_info;
+        if !screens.is_empty() {
+            return Ok(screens
+                .into_iter()
+                .enumerate()
+                .map(|(i, screen)| config::Monitor {
+                    name: format!("xinerama-{}", i),
+                    edid: None,
+                    config: None,
+                    desktops: Vec::new(),
+                    x: screen.x_org,
+                    y: screen.y_org,
+                    width: screen.width,
+                    height: screen.height,
+                })
+                .collect());
+        }
+    }
+
+    let geometry = conn.get_geometry(root_window)?.reply()?;
+    Ok(vec![config::Monitor {
+        name: "screen".to_string(),
+        edid: None,
+        config: None,
+        desktops: Vec::new(),
+        x: 0,
+        y: 0,
+        width: geometry.width,
+        height: geometry.height,
+    }])
+}
+
+/// RandR 1.5 monitors, including the ones added with `xrandr --setmonitor`.
+fn get_logical_monitors<C: Connection>(
+    conn: &C,
+    root_window: Window,
+) -> Result<Vec<config::Monitor>, ReplyOrIdError> {
+    let infos = randr::get_monitors(conn, root_window, true)?
+        .reply()?
+        .monitors;
+    let edid_atom = conn.intern_atom(false, b"EDID")?.reply()?.atom;
+
+    let mut monitors = Vec::with_capacity(infos.len());
+    for info in &infos {
+        let name = conn.get_atom_name(info.name)?.reply()?.name;
+        // a monitor split into several logical ones would match all of them by EDID
+        let is_shared = |output: &randr::Output| -> bool {
+            infos
+                .iter()
+                .filter(|other| other.outputs.contains(output))
+                .count()
+                > 1
+        };
+        let edid = match info.outputs[..] {
+            [output] if !is_shared(&output) => get_edid(conn, output, edid_atom)?,
+            _ => None,
+        };
+        monitors.push(config::Monitor {
+            name: String::from_utf8_lossy(&name).into_owned(),
+            edid,
+            config: None,
+            desktops: Vec::new(),
+            x: info.x,
+            y: info.y,
+            width: info.width,
+            height: info.height,
+        });
+    }
+    Ok(monitors)
+}
+
+/// The CRTC rectangle of every connected output.
+fn get_crtc_monitors<C: Connection>(
+    conn: &C,
+    root_window: Window,
 ) -> Result<Vec<config::Monitor>, ReplyOrIdError> {
     let mut monitors = Vec::new();
+    let edid_atom = conn.intern_atom(false, b"EDID")?.reply()?.atom;
     let screen_resources = randr::get_screen_resources(conn, root_window)?.reply()?;
     for s in screen_resources.outputs {
-        // can't combine these if statements because it's unstable
         if let Ok(output_info) =
-            randr::get_output_info(&conn, s, screen_resources.config_timestamp)?.reply()
+            randr::get_output_info(conn, s, screen_resources.config_timestamp)?.reply()
+            && output_info.connection == randr::Connection::CONNECTED
         {
-            if output_info.connection == randr::Connection::CONNECTED {
-                match randr::get_crtc_info(
-                    &conn,
-                    output_info.crtc,
-                    screen_resources.config_timestamp,
-                )?
-                .reply()
-                {
-                    Ok(crtc_info) => {
-                        monitors.push(config::Monitor {
-                            name: String::from_utf8(output_info.name).unwrap(),
-                            config: None,
-                            x: crtc_info.x.try_into().unwrap(),
-                            y: crtc_info.y.try_into().unwrap(),
-                            width: crtc_info.width,
-                            height: crtc_info.height,
-                        });
-                    }
-                    Err(e) => {
-                        dbg!(e);
-                    }
-                }
-            }
+            // connected outputs without an active CRTC are switched off
+            let Ok(crtc_info) =
+                randr::get_crtc_info(conn, output_info.crtc, screen_resources.config_timestamp)?
+                    .reply()
+            else {
+                continue;
+            };
+            monitors.push(config::Monitor {
+                name: String::from_utf8_lossy(&output_info.name).into_owned(),
+                edid: get_edid(conn, s, edid_atom)?,
+                config: None,
+                desktops: Vec::new(),
+                x: crtc_info.x,
+                y: crtc_info.y,
+                width: crtc_info.width,
+                height: crtc_info.height,
+            });
         }
     }
 
     Ok(monitors)
 }
+
+/// Identity from the `EDID` property of `output`, `None` if the output has no (valid) EDID.
+fn get_edid<C: Connection>(
+    conn: &C,
+    output: randr::Output,
+    edid_atom: Atom,
+) -> Result<Option<Edid>, ReplyOrIdError> {
+    // 128 bytes base block, the property length is in 32 bit units
+    let reply =
+        randr::get_output_property(conn, output, edid_atom, AtomEnum::ANY, 0, 32, false, false)?
+            .reply()?;
+    Ok(Edid::parse(&reply.data))
+}
+
+fn intern<C: Connection>(conn: &C, name: &[u8]) -> Result<Atom, ReplyOrIdError> {
+    Ok(conn.intern_atom(false, name)?.reply()?.atom)
+}
+
+fn get_cardinals<C: Connection>(
+    conn: &C,
+    window: Window,
+    property: Atom,
+) -> Result<Vec<u32>, ReplyOrIdError> {
+    let reply = conn
+        .get_property(false, window, property, AtomEnum::CARDINAL, 0, u32::MAX)?
+        .reply()?;
+    Ok(reply.value32().map(Iterator::collect).unwrap_or_default())
+}
+
+/// Struts of all managed clients and top level windows.
+/// `_NET_WM_STRUT_PARTIAL` is preferred over the older `_NET_WM_STRUT`.
+pub fn get_struts<C: Connection>(
+    conn: &C,
+    root_window: Window,
+) -> Result<Vec<Strut>, ReplyOrIdError> {
+    let strut_partial = intern(conn, b"_NET_WM_STRUT_PARTIAL")?;
+    let strut = intern(conn, b"_NET_WM_STRUT")?;
+    let client_list = intern(conn, b"_NET_CLIENT_LIST")?;
+    let root = conn.get_geometry(root_window)?.reply()?;
+
+    let mut windows: Vec<Window> = conn
+        .get_property(
+            false,
+            root_window,
+            client_list,
+            AtomEnum::WINDOW,
+            0,
+            u32::MAX,
+        )?
+        .reply()?
+        .value32()
+        .map(Iterator::collect)
+        .unwrap_or_default();
+    for child in conn.query_tree(root_window)?.reply()?.children {
+        if !windows.contains(&child) {
+            windows.push(child);
+        }
+    }
+
+    let mut struts = Vec::new();
+    for window in windows {
+        // windows may be gone by the time we ask for their properties
+        let Ok(attributes) = conn.get_window_attributes(window)?.reply() else {
+            continue;
+        };
+        // hidden panels don't reserve any space
+        if attributes.map_state != MapState::VIEWABLE {
+            continue;
+        }
+        let Ok(partial) = get_cardinals(conn, window, strut_partial) else {
+            continue;
+        };
+        let found = match Strut::from_partial(&partial) {
+            Some(found) => Some(found),
+            None => get_cardinals(conn, window, strut)
+                .ok()
+                .and_then(|full| Strut::from_full(&full, root.width, root.height)),
+        };
+        struts.extend(found);
+    }
+    Ok(struts)
+}
+
+/// True if `window` reserves space at a screen edge with `_NET_WM_STRUT_PARTIAL` or
+/// `_NET_WM_STRUT`.
+pub fn has_strut<C: Connection>(conn: &C, window: Window) -> Result<bool, ReplyOrIdError> {
+    for name in [&b"_NET_WM_STRUT_PARTIAL"[..], b"_NET_WM_STRUT"] {
+        if !get_cardinals(conn, window, intern(conn, name)?)?.is_empty() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Index of the current desktop from `_NET_CURRENT_DESKTOP`, 0 without a window manager
+/// that sets it.
+pub fn get_current_desktop<C: Connection>(
+    conn: &C,
+    root_window: Window,
+) -> Result<u32, ReplyOrIdError> {
+    let current = get_cardinals(conn, root_window, intern(conn, b"_NET_CURRENT_DESKTOP")?)?;
+    Ok(current.first().copied().unwrap_or(0))
+}
+
+/// Desktop names from `_NET_DESKTOP_NAMES`, indexed like the desktops.
+pub fn get_desktop_names<C: Connection>(
+    conn: &C,
+    root_window: Window,
+) -> Result<Vec<String>, ReplyOrIdError> {
+    let names = intern(conn, b"_NET_DESKTOP_NAMES")?;
+    let utf8_string = intern(conn, b"UTF8_STRING")?;
+    let reply = conn
+        .get_property(false, root_window, names, utf8_string, 0, u32::MAX)?
+        .reply()?;
+    // the names are null terminated, so the last split is always empty
+    let mut names: Vec<String> = reply
+        .value
+        .split(|b| *b == 0)
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect();
+    if names.last().is_some_and(String::is_empty) {
+        names.pop();
+    }
+    Ok(names)
+}
+
+/// `_NET_WORKAREA` of the current desktop, if the window manager sets it.
+pub fn get_net_workarea<C: Connection>(
+    conn: &C,
+    root_window: Window,
+) -> Result<Option<Area>, ReplyOrIdError> {
+    let workarea = get_cardinals(conn, root_window, intern(conn, b"_NET_WORKAREA")?)?;
+    let desktop = get_current_desktop(conn, root_window)? as usize;
+    Ok(workarea.chunks_exact(4).nth(desktop).map(|area| Area {
+        x: area[0] as i16,
+        y: area[1] as i16,
+        width: area[2] as u16,
+        height: area[3] as u16,
+    }))
+}
+
+/// The work area of every monitor. Struts are used when any window sets them, because
+/// `_NET_WORKAREA` is a single rectangle for all monitors, which only fits one monitor.
+pub fn get_work_areas<C: Connection>(
+    conn: &C,
+    root_window: Window,
+    monitors: &[config::Monitor],
+) -> Result<Vec<Area>, ReplyOrIdError> {
+    let struts = get_struts(conn, root_window)?;
+    if !struts.is_empty() {
+        let root = conn.get_geometry(root_window)?.reply()?;
+        return Ok(monitors
+            .iter()
+            .map(|monitor| workarea::work_area(monitor.area(), root.width, root.height, &struts))
+            .collect());
+    }
+
+    let net_workarea = get_net_workarea(conn, root_window)?;
+    Ok(monitors
+        .iter()
+        .map(|monitor| {
+            net_workarea
+                .map(|workarea| monitor.area().intersect(&workarea))
+                .filter(|area| area.width > 0 && area.height > 0)
+                .unwrap_or(monitor.area())
+        })
+        .collect())
+}
+
+/// The client window of the top level `window`: the window itself or, under a reparenting
+/// window manager, the descendant that carries `WM_STATE`.
+pub fn find_client<C: Connection>(conn: &C, window: Window) -> Result<Window, ReplyOrIdError> {
+    let wm_state = intern(conn, b"WM_STATE")?;
+    let mut queue = vec![window];
+    while let Some(win) = queue.pop() {
+        let reply = conn
+            .get_property(false, win, wm_state, AtomEnum::ANY, 0, 0)?
+            .reply()?;
+        if reply.type_ != x11rb::NONE {
+            return Ok(win);
+        }
+        queue.extend(conn.query_tree(win)?.reply()?.children);
+    }
+    Ok(window)
+}
+
+/// `WM_CLASS`, `WM_WINDOW_ROLE`, title and window type of the client in the top level
+/// `window`.
+pub fn get_window_info<C: Connection>(
+    conn: &C,
+    window: Window,
+) -> Result<WindowInfo, ReplyOrIdError> {
+    let client = find_client(conn, window)?;
+    let get_string = |property: Atom, type_: Atom| -> Result<Vec<u8>, ReplyOrIdError> {
+        Ok(conn
+            .get_property(false, client, property, type_, 0, u32::MAX)?
+            .reply()?
+            .value)
+    };
+
+    let (instance, class) = WindowInfo::parse_class(&get_string(
+        AtomEnum::WM_CLASS.into(),
+        AtomEnum::STRING.into(),
+    )?);
+    let role = get_string(intern(conn, b"WM_WINDOW_ROLE")?, AtomEnum::STRING.into())?;
+    let mut title = get_string(
+        intern(conn, b"_NET_WM_NAME")?,
+        intern(conn, b"UTF8_STRING")?,
+    )?;
+    if title.is_empty() {
+        title = get_string(AtomEnum::WM_NAME.into(), AtomEnum::ANY.into())?;
+    }
+
+    let types = conn
+        .get_property(
+            false,
+            client,
+            intern(conn, b"_NET_WM_WINDOW_TYPE")?,
+            AtomEnum::ATOM,
+            0,
+            u32::MAX,
+        )?
+        .reply()?;
+    let mut window_type = None;
+    for atom in types.value32().into_iter().flatten() {
+        let name = conn.get_atom_name(atom)?.reply()?.name;
+        window_type = WindowType::from_atom_name(&String::from_utf8_lossy(&name));
+        if window_type.is_some() {
+            break;
+        }
+    }
+
+    Ok(WindowInfo {
+        instance,
+        class,
+        role: (!role.is_empty()).then(|| String::from_utf8_lossy(&role).into_owned()),
+        title: String::from_utf8_lossy(&title).into_owned(),
+        window_type,
+    })
+}
@@ -0,0 +1,104 @@
+use core::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// What identifies an application window across sessions, read from the properties of its
+/// client window.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowInfo {
+    /// Instance part of `WM_CLASS`, usually the name the program was started as.
+    pub instance: String,
+    /// Class part of `WM_CLASS`, e.g. `Firefox`.
+    pub class: String,
+    /// `WM_WINDOW_ROLE`, set by applications with several kinds of windows.
+    pub role: Option<String>,
+    /// `_NET_WM_NAME`, or `WM_NAME` for clients that don't set it.
+    pub title: String,
+    /// First type of `_NET_WM_WINDOW_TYPE` this build knows.
+    pub window_type: Option<WindowType>,
+}
+
+impl WindowInfo {
+    /// Splits the two null terminated strings of a `WM_CLASS` value into instance and class.
+    pub fn parse_class(value: &[u8]) -> (String, String) {
+        let mut parts = value
+            .split(|b| *b == 0)
+            .map(|part| String::from_utf8_lossy(part).into_owned());
+        let instance = parts.next().unwrap_or_default();
+        let class = parts.next().unwrap_or_default();
+        (instance, class)
+    }
+}
+
+/// The `_NET_WM_WINDOW_TYPE` values a rule can match on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowType {
+    Normal,
+    Dialog,
+    Utility,
+    Toolbar,
+    Splash,
+    Menu,
+    Dock,
+    Desktop,
+}
+
+impl WindowType {
+    pub const ALL: [WindowType; 8] = [
+        WindowType::Normal,
+        WindowType::Dialog,
+        WindowType::Utility,
+        WindowType::Toolbar,
+        WindowType::Splash,
+        WindowType::Menu,
+        WindowType::Dock,
+        WindowType::Desktop,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WindowType::Normal => "normal",
+            WindowType::Dialog => "dialog",
+            WindowType::Utility => "utility",
+            WindowType::Toolbar => "toolbar",
+            WindowType::Splash => "splash",
+            WindowType::Menu => "menu",
+            WindowType::Dock => "dock",
+            WindowType::Desktop => "desktop",
+        }
+    }
+
+    /// The type for an atom name like `_NET_WM_WINDOW_TYPE_DIALOG`.
+    pub fn from_atom_name(atom_name: &str) -> Option<WindowType> {
+        let name = atom_name.strip_prefix("_NET_WM_WINDOW_TYPE_")?;
+        WindowType::ALL
+            .into_iter()
+            .find(|window_type| name.eq_ignore_ascii_case(window_type.name()))
+    }
+}
+
+impl fmt::Display for WindowType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for WindowType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WindowType::ALL
+            .into_iter()
+            .find(|window_type| window_type.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = WindowType::ALL.iter().map(|t| t.name()).collect();
+                format!(
+                    "unknown window type {:?}, expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
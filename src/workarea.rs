@@ -0,0 +1,130 @@
+/// A rectangle in root window coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Area {
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Area {
+    fn right(&self) -> i32 {
+        self.x as i32 + self.width as i32
+    }
+
+    fn bottom(&self) -> i32 {
+        self.y as i32 + self.height as i32
+    }
+
+    fn from_edges(left: i32, top: i32, right: i32, bottom: i32) -> Area {
+        Area {
+            x: left as i16,
+            y: top as i16,
+            width: (right - left).max(0) as u16,
+            height: (bottom - top).max(0) as u16,
+        }
+    }
+
+    /// The part of `self` that is also covered by `other`.
+    pub fn intersect(&self, other: &Area) -> Area {
+        Area::from_edges(
+            self.x.max(other.x) as i32,
+            self.y.max(other.y) as i32,
+            self.right().min(other.right()),
+            self.bottom().min(other.bottom()),
+        )
+    }
+}
+
+/// Space a panel or dock reserves at the edges of the root window, as in
+/// `_NET_WM_STRUT_PARTIAL`. Start and end values are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Strut {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+    pub left_start_y: u32,
+    pub left_end_y: u32,
+    pub right_start_y: u32,
+    pub right_end_y: u32,
+    pub top_start_x: u32,
+    pub top_end_x: u32,
+    pub bottom_start_x: u32,
+    pub bottom_end_x: u32,
+}
+
+impl Strut {
+    /// Parses the 12 values of `_NET_WM_STRUT_PARTIAL`.
+    pub fn from_partial(values: &[u32]) -> Option<Strut> {
+        let v: &[u32; 12] = values.get(..12)?.try_into().ok()?;
+        Some(Strut {
+            left: v[0],
+            right: v[1],
+            top: v[2],
+            bottom: v[3],
+            left_start_y: v[4],
+            left_end_y: v[5],
+            right_start_y: v[6],
+            right_end_y: v[7],
+            top_start_x: v[8],
+            top_end_x: v[9],
+            bottom_start_x: v[10],
+            bottom_end_x: v[11],
+        })
+    }
+
+    /// Parses the 4 values of the older `_NET_WM_STRUT`, which always spans the whole edge
+    /// of a `root_width`x`root_height` root window.
+    pub fn from_full(values: &[u32], root_width: u16, root_height: u16) -> Option<Strut> {
+        let v: &[u32; 4] = values.get(..4)?.try_into().ok()?;
+        let max_x = (root_width as u32).saturating_sub(1);
+        let max_y = (root_height as u32).saturating_sub(1);
+        Some(Strut {
+            left: v[0],
+            right: v[1],
+            top: v[2],
+            bottom: v[3],
+            left_end_y: max_y,
+            right_end_y: max_y,
+            top_end_x: max_x,
+            bottom_end_x: max_x,
+            ..Strut::default()
+        })
+    }
+}
+
+/// Whether the inclusive range `start..=end` overlaps `from..to`.
+fn spans(start: u32, end: u32, from: i32, to: i32) -> bool {
+    (start as i64) < to as i64 && (end as i64) >= from as i64
+}
+
+/// The part of `monitor` not covered by any of `struts` on a `root_width`x`root_height`
+/// root window.
+pub fn work_area(monitor: Area, root_width: u16, root_height: u16, struts: &[Strut]) -> Area {
+    let (mut left, mut top) = (monitor.x as i32, monitor.y as i32);
+    let (mut right, mut bottom) = (monitor.right(), monitor.bottom());
+
+    for strut in struts {
+        if strut.left > 0 && spans(strut.left_start_y, strut.left_end_y, top, bottom) {
+            left = left.max(strut.left as i32);
+        }
+        if strut.right > 0 && spans(strut.right_start_y, strut.right_end_y, top, bottom) {
+            right = right.min(root_width as i32 - strut.right as i32);
+        }
+        if strut.top > 0 && spans(strut.top_start_x, strut.top_end_x, left, right) {
+            top = top.max(strut.top as i32);
+        }
+        if strut.bottom > 0 && spans(strut.bottom_start_x, strut.bottom_end_x, left, right) {
+            bottom = bottom.min(root_height as i32 - strut.bottom as i32);
+        }
+    }
+
+    // a strut that lies completely outside of the monitor leaves it as it is
+    Area::from_edges(
+        left.min(monitor.right()),
+        top.min(monitor.bottom()),
+        right.max(monitor.x as i32),
+        bottom.max(monitor.y as i32),
+    )
+}
@@ -0,0 +1,568 @@
+use std::path::{Path, PathBuf};
+
+use lancy_zones::config::{
+    self, BACKUP_COUNT, CONFIG_VERSION, Config, ConfigError, Desktop, Length, Modifier, Monitor,
+    OffsetError, Profile, RuleSet, Severity, Theme, ZoneLabels,
+};
+use lancy_zones::window::{WindowInfo, WindowType};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+fn assert_fixture_content(config: &Config) {
+    assert_eq!(config.version, CONFIG_VERSION);
+    assert_eq!(config.alpha, 0.5);
+    assert_eq!(config.theme, Theme::default());
+    assert_eq!(config.monitors.len(), 2);
+    assert_eq!(config.activation_modifiers, vec![Modifier::Control]);
+    assert_eq!(config.drop_button, 1);
+
+    let dp0 = config.get_monitor_config("DP-0").unwrap();
+    assert_eq!(dp0.zones.len(), 1);
+    assert_eq!(dp0.zones[0].name, "main");
+    assert_eq!(dp0.zones[0].x, Length::Px(1280));
+    assert_eq!(dp0.zones[0].width, Length::Px(2560));
+}
+
+#[test]
+fn loads_v0() {
+    let config = config::load_cfg_file(&fixture("config_v0.json")).unwrap();
+    assert_fixture_content(&config);
+}
+
+#[test]
+fn loads_v1() {
+    let config = config::load_cfg_file(&fixture("config_v1.json")).unwrap();
+    assert_fixture_content(&config);
+}
+
+#[test]
+fn loads_v2() {
+    let config = config::load_cfg_file(&fixture("config_v2.json")).unwrap();
+    assert_eq!(config.version, CONFIG_VERSION);
+
+    let dp0 = config.get_monitor_config("DP-0").unwrap();
+    assert_eq!(dp0.zones[0].x, Length::Px(1280));
+    assert_eq!(dp0.zones[1].x, Length::Percent(25.0));
+    assert_eq!(dp0.zones[1].width, Length::Fraction(0.5));
+}
+
+#[test]
+fn loads_v3() {
+    let config = config::load_cfg_file(&fixture("config_v3.json")).unwrap();
+    assert_fixture_content(&config);
+}
+
+#[test]
+fn loads_v4() {
+    let config = config::load_cfg_file(&fixture("config_v4.json")).unwrap();
+    assert_eq!(
+        config.activation_modifiers,
+        vec![Modifier::Super, Modifier::Shift]
+    );
+    assert_eq!(config.drop_button, 3);
+}
+
+#[test]
+fn loads_v5() {
+    let config = config::load_cfg_file(&fixture("config_v5.json")).unwrap();
+    assert_eq!(config.theme.line_thickness, 2);
+    assert_eq!(config.theme.zone_fill.to_string(), "#1e1e2e");
+
+    let dp0 = config.get_monitor_config("DP-0").unwrap();
+    let theme = config.theme_for(Some(dp0));
+    assert_eq!(theme.active_fill.to_string(), "#f38ba8");
+    assert_eq!(theme.line_thickness, 4);
+    assert_eq!(theme.zone_border, config.theme.zone_border);
+    assert!(
+        config
+            .get_monitor_config("HDMI-0")
+            .unwrap()
+            .theme
+            .is_empty()
+    );
+}
+
+#[test]
+fn loads_v6() {
+    let config = config::load_cfg_file(&fixture("config_v6.json")).unwrap();
+    assert_fixture_content(&config);
+    assert_eq!(config.zone_labels, ZoneLabels::Indices);
+
+    let dp0 = config.get_monitor_config("DP-0").unwrap();
+    assert_eq!(config.theme_for(Some(dp0)).label.to_string(), "#f38ba8");
+    assert_eq!(
+        config.zone_labels.label(0, &dp0.zones[0]),
+        Some("1".to_string())
+    );
+}
+
+#[test]
+fn loads_v7() {
+    let config = config::load_cfg_file(&fixture("config_v7.json")).unwrap();
+    assert_fixture_content(&config);
+    assert_eq!(config.profiles.len(), 2);
+    assert_eq!(config.profiles[1].assignments["DP-0"], "DP-0");
+    assert!(config.validate().is_empty());
+}
+
+#[test]
+fn loads_v8() {
+    let config = config::load_cfg_file(&fixture("config_v8.json")).unwrap();
+    assert_fixture_content(&config);
+    assert_eq!(config.monitors[0].edid, None);
+    assert_eq!(
+        config.monitors[1].edid.as_ref().unwrap().to_string(),
+        "DEL-A0A7-4C383041"
+    );
+}
+
+#[test]
+fn loads_v9() {
+    let config = config::load_cfg_file(&fixture("config_v9.json")).unwrap();
+    assert_fixture_content(&config);
+    assert!(config.get_monitor_config("DP-0").unwrap().work_area);
+    assert!(!config.get_monitor_config("HDMI-0").unwrap().work_area);
+}
+
+#[test]
+fn loads_v10() {
+    let config = config::load_cfg_file(&fixture("config_v10.json")).unwrap();
+    assert_fixture_content(&config);
+    let dp0 = &config.monitors[1];
+    assert_eq!(dp0.desktops[0].desktop, Desktop::Index(0));
+    assert_eq!(dp0.desktops[1].desktop, Desktop::Name("chat".to_string()));
+    assert!(config.validate().is_empty());
+}
+
+#[test]
+fn loads_v11() {
+    let config = config::load_cfg_file(&fixture("config_v11.json")).unwrap();
+    assert_fixture_content(&config);
+    assert!(config.restore_size);
+
+    // older configs keep the zone's size
+    let config = config::load_cfg_file(&fixture("config_v10.json")).unwrap();
+    assert!(!config.restore_size);
+}
+
+#[test]
+fn loads_v12() {
+    let config = config::load_cfg_file(&fixture("config_v12.json")).unwrap();
+    assert_fixture_content(&config);
+    assert_eq!(config.rules.len(), 2);
+    assert_eq!(config.rules[1].window_type, Some(WindowType::Normal));
+    assert!(config.validate().is_empty());
+}
+
+#[test]
+fn matches_rules_on_window_properties() {
+    let config = config::load_cfg_file(&fixture("config_v12.json")).unwrap();
+    let rules = RuleSet::new(&config.rules);
+    let mut info = WindowInfo {
+        instance: "code".to_string(),
+        class: "Code".to_string(),
+        role: None,
+        title: "main.rs - Visual Studio Code".to_string(),
+        window_type: Some(WindowType::Normal),
+    };
+    assert_eq!(rules.matching(&info).unwrap().name, "editor");
+
+    info.window_type = Some(WindowType::Dialog);
+    assert!(rules.matching(&info).is_none());
+
+    info.class = "firefox".to_string();
+    assert_eq!(rules.matching(&info).unwrap().name, "browser");
+}
+
+#[test]
+fn validates_rules() {
+    let mut config = config::load_cfg_file(&fixture("config_v12.json")).unwrap();
+    config.rules[0].zone = "left".to_string();
+    config.rules[1].title = Some("(".to_string());
+    let mut catch_all = config.rules[0].clone();
+    catch_all.class = None;
+    catch_all.zone = "main".to_string();
+    config.rules.push(catch_all);
+
+    let locations: Vec<String> = config
+        .validate()
+        .into_iter()
+        .filter(|d| d.is_error())
+        .map(|d| d.location)
+        .collect();
+    assert_eq!(
+        locations,
+        [
+            "rule browser",
+            "rule editor",
+            "rule browser",
+            "rule browser"
+        ]
+    );
+}
+
+#[test]
+fn picks_config_per_desktop() {
+    let config = config::load_cfg_file(&fixture("config_v10.json")).unwrap();
+    let dp0 = &config.monitors[1];
+    assert_eq!(dp0.config_for_desktop(0, Some("code")), Some("DP-0"));
+    assert_eq!(dp0.config_for_desktop(2, Some("chat")), Some("HDMI-0"));
+    assert_eq!(dp0.config_for_desktop(3, None), Some("DP-0"));
+    assert!(dp0.uses("HDMI-0"));
+
+    // desktop assignments follow the monitor to other outputs
+    let mut moved = dp0.clone();
+    moved.name = "DP-3".to_string();
+    moved.desktops.clear();
+    let monitors = config.assign_detected(vec![moved]);
+    assert_eq!(monitors[0].desktops, dp0.desktops);
+
+    assert_eq!("2".parse(), Ok(Desktop::Index(2)));
+    assert_eq!("chat".parse(), Ok(Desktop::Name("chat".to_string())));
+}
+
+#[test]
+fn v5_configs_keep_unlabelled_zones() {
+    let config = config::load_cfg_file(&fixture("config_v5.json")).unwrap();
+    assert_eq!(config.zone_labels, ZoneLabels::None);
+    assert_eq!(config.theme.label, Theme::default().label);
+}
+
+#[test]
+fn v4_line_thickness_moves_into_theme() {
+    let mut doc = serde_json::json!({ "version": 4, "line_thickness": 7 });
+    config::migrate(&mut doc).unwrap();
+    assert_eq!(doc["theme"]["line_thickness"], 7);
+    assert_eq!(doc["theme"]["zone_border"], "#ffffff");
+    assert!(doc.get("line_thickness").is_none());
+}
+
+#[test]
+fn v2_monitors_reference_configs_by_name() {
+    let config = config::load_cfg_file(&fixture("config_v2.json")).unwrap();
+    let dp0 = config.monitors.iter().find(|m| m.name == "DP-0").unwrap();
+    assert_eq!(dp0.config.as_deref(), Some("DP-0"));
+    assert_eq!(config.get_assigned_config(dp0).unwrap().zones.len(), 2);
+    assert_eq!(config.monitor_configs.len(), 2);
+}
+
+#[test]
+fn v2_orphaned_monitor_config_is_kept() {
+    let mut doc = serde_json::json!({
+        "version": 2,
+        "monitors": [
+            {"name": "DP-0", "config": {"name": "old", "zones": [
+                {"name": "main", "x": 0, "y": 0, "width": 10, "height": 10}
+            ]}, "x": 0, "y": 0, "width": 100, "height": 100},
+            {"name": "DP-1", "config": null, "x": 100, "y": 0, "width": 100, "height": 100}
+        ],
+        "monitor_configs": [],
+        "alpha": 0.5,
+        "line_thickness": 3
+    });
+    config::migrate(&mut doc).unwrap();
+    let config: Config = serde_json::from_value(doc).unwrap();
+
+    assert_eq!(config.monitors[0].config.as_deref(), Some("old"));
+    assert_eq!(config.monitors[1].config, None);
+    assert_eq!(
+        config.get_monitor_config("old").unwrap().zones[0].name,
+        "main"
+    );
+}
+
+#[test]
+fn parses_lengths() {
+    assert_eq!("40".parse(), Ok(Length::Px(40)));
+    assert_eq!("40px".parse(), Ok(Length::Px(40)));
+    assert_eq!("33%".parse(), Ok(Length::Percent(33.0)));
+    assert_eq!("0.25".parse(), Ok(Length::Fraction(0.25)));
+    assert!("abc".parse::<Length>().is_err());
+}
+
+#[test]
+fn resolves_mixed_zone() {
+    let zone = config::Zone {
+        name: "left".to_string(),
+        x: Length::Px(0),
+        y: Length::Px(40),
+        width: Length::Percent(33.0),
+        height: Length::Fraction(0.5),
+    };
+    let resolved = zone.resolve(1920, 1080);
+    assert_eq!(
+        (resolved.x, resolved.y, resolved.width, resolved.height),
+        (0, 40, 634, 540)
+    );
+
+    let scaled = zone.to_fractions(1920, 1080).resolve(960, 540);
+    assert_eq!(
+        (scaled.x, scaled.y, scaled.width, scaled.height),
+        (0, 20, 317, 270)
+    );
+}
+
+#[test]
+fn lengths_round_trip() {
+    let lengths = vec![Length::Px(40), Length::Percent(33.0), Length::Fraction(1.0)];
+    let json = serde_json::to_string(&lengths).unwrap();
+    assert_eq!(json, r#"[40,"33%",1.0]"#);
+    assert_eq!(serde_json::from_str::<Vec<Length>>(&json).unwrap(), lengths);
+}
+
+#[test]
+fn offsets_lengths_of_the_same_unit() {
+    assert_eq!(Length::Px(10).offset(Length::Px(-20)), Ok(Length::Px(-10)));
+    assert_eq!(
+        Length::Percent(25.0).offset(Length::Percent(5.0)),
+        Ok(Length::Percent(30.0))
+    );
+    assert_eq!(
+        Length::Fraction(0.5).offset(Length::Px(0)),
+        Ok(Length::Fraction(0.5))
+    );
+    assert_eq!(
+        Length::Px(10).offset(Length::Percent(5.0)),
+        Err(OffsetError::MixedUnits)
+    );
+    assert_eq!(
+        Length::Fraction(0.5).offset(Length::Px(10)),
+        Err(OffsetError::MixedUnits)
+    );
+    assert_eq!(
+        Length::Px(30000).offset(Length::Px(30000)),
+        Err(OffsetError::Overflow)
+    );
+}
+
+#[test]
+fn edits_zones_by_name() {
+    let zone = |name: &str| -> config::Zone {
+        config::Zone {
+            name: name.to_string(),
+            x: Length::Px(0),
+            y: Length::Px(0),
+            width: Length::Fraction(0.5),
+            height: Length::Fraction(1.0),
+        }
+    };
+    let mut monitor_config = config::MonitorConfig {
+        name: "DP-0".to_string(),
+        zones: vec![zone("left"), zone("right")],
+        theme: Default::default(),
+        work_area: false,
+    };
+
+    assert!(matches!(
+        monitor_config.remove_zone("centre"),
+        Err(ConfigError::MissingZone { config, zone }) if config == "DP-0" && zone == "centre"
+    ));
+    assert!(matches!(
+        monitor_config.get_zone_mut("centre"),
+        Err(ConfigError::MissingZone { zone, .. }) if zone == "centre"
+    ));
+    assert!(matches!(
+        monitor_config.rename_zone("centre", "middle"),
+        Err(ConfigError::MissingZone { zone, .. }) if zone == "centre"
+    ));
+    assert!(matches!(
+        monitor_config.rename_zone("left", "right"),
+        Err(ConfigError::ZoneExists { zone, .. }) if zone == "right"
+    ));
+    // renaming to the same name is a no-op
+    monitor_config.rename_zone("left", "left").unwrap();
+
+    monitor_config.rename_zone("left", "main").unwrap();
+    assert_eq!(monitor_config.remove_zone("main").unwrap().name, "main");
+    assert_eq!(monitor_config.zones.len(), 1);
+}
+
+#[test]
+fn rejects_newer_version() {
+    let mut doc = serde_json::json!({ "version": CONFIG_VERSION + 1 });
+    assert_eq!(config::migrate(&mut doc), Err(CONFIG_VERSION as u64 + 1));
+}
+
+#[test]
+fn saved_config_is_current_version() {
+    let dir = std::env::temp_dir().join(format!("lancy-zones-save-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.json");
+
+    let config = config::load_cfg_file(&fixture("config_v0.json")).unwrap();
+    config::save_cfg_file(&path, &config).unwrap();
+    let raw: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(raw["version"], CONFIG_VERSION);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rotates_and_restores_backups() {
+    let dir = std::env::temp_dir().join(format!("lancy-zones-backup-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.json");
+    let alpha = |path: &Path| -> f32 { config::load_cfg_file(path).unwrap().alpha };
+
+    let mut config = config::load_cfg_file(&fixture("config_v12.json")).unwrap();
+    for i in 0..BACKUP_COUNT + 2 {
+        config.alpha = i as f32 / 10.0;
+        config::save_cfg_file(&path, &config).unwrap();
+    }
+    // the first save had nothing to back up, the oldest one fell off
+    for n in 1..=BACKUP_COUNT {
+        let expected = (BACKUP_COUNT + 1 - n) as f32 / 10.0;
+        assert_eq!(alpha(&config::backup_path(&path, n)), expected);
+    }
+    assert!(!config::backup_path(&path, BACKUP_COUNT + 1).exists());
+
+    let current = alpha(&path);
+    let restored = config::restore_backup(&path, 3).unwrap();
+    assert_eq!(alpha(&path), restored.alpha);
+    assert_eq!(restored.alpha, (BACKUP_COUNT - 2) as f32 / 10.0);
+    assert_eq!(alpha(&config::backup_path(&path, 1)), current);
+
+    assert!(matches!(
+        config::restore_backup(&path, BACKUP_COUNT + 1),
+        Err(ConfigError::MissingBackup(_))
+    ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn reports_parse_position() {
+    let dir = std::env::temp_dir().join(format!("lancy-zones-parse-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.json");
+    std::fs::write(&path, "{\n  \"monitors\": [,\n}").unwrap();
+
+    match config::load_cfg_file(&path) {
+        Err(ConfigError::Parse { line, column, .. }) => assert_eq!((line, column), (2, 16)),
+        other => panic!("expected parse error, got {:?}", other),
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn validates_config() {
+    let mut config = config::load_cfg_file(&fixture("config_v1.json")).unwrap();
+    assert!(config.validate().is_empty());
+
+    config.alpha = 1.5;
+    let dp0 = config.get_monitor_config_mut("DP-0").unwrap();
+    let mut duplicate = dp0.zones[0].clone();
+    duplicate.width = Length::Px(-10);
+    dp0.zones[0].x = Length::Px(4000);
+    dp0.zones.push(duplicate);
+    config.monitors[0].config = Some("gone".to_string());
+
+    let diagnostics: Vec<(Severity, String)> = config
+        .validate()
+        .into_iter()
+        .map(|d| (d.severity, d.location))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        vec![
+            (Severity::Error, "alpha".to_string()),
+            (Severity::Error, "monitor HDMI-0".to_string()),
+            (Severity::Warning, "config DP-0, zone main".to_string()),
+            (Severity::Error, "config DP-0, zone main".to_string()),
+            (Severity::Error, "config DP-0, zone main".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn assigns_configs_to_detected_monitors() {
+    let config = config::load_cfg_file(&fixture("config_v6.json")).unwrap();
+    let mut docked = config.monitors[1].clone();
+    docked.config = None;
+    docked.x = 1920;
+    let mut external = docked.clone();
+    external.name = "DP-1".to_string();
+
+    let monitors = config.assign_detected(vec![docked, external]);
+    assert_eq!(monitors.len(), 2);
+    assert_eq!(monitors[0].config.as_deref(), Some("DP-0"));
+    assert_eq!(monitors[0].x, 1920);
+    assert_eq!(monitors[1].config, None);
+}
+
+#[test]
+fn matching_profile_decides_assignments() {
+    let config = config::load_cfg_file(&fixture("config_v7.json")).unwrap();
+
+    // both desk outputs connected, HDMI-0 has no assignment in the profile
+    let monitors = config.assign_detected(config.monitors.clone());
+    assert_eq!(
+        config.matching_profile(&config.monitors).unwrap().name,
+        "desk"
+    );
+    assert_eq!(monitors[0].config, None);
+    assert_eq!(monitors[1].config.as_deref(), Some("DP-0"));
+
+    // no profile for a single DP-0, the stored assignment is used
+    let monitors = config.assign_detected(vec![config.monitors[1].clone()]);
+    assert_eq!(monitors[0].config.as_deref(), Some("DP-0"));
+
+    let profile = Profile::from_monitors("saved", &config.monitors);
+    assert_eq!(profile.outputs, vec!["DP-0", "HDMI-0"]);
+    assert_eq!(profile.assignments.len(), 2);
+    assert!(profile.matches(&config.monitors));
+}
+
+#[test]
+fn matches_monitors_by_edid_first() {
+    let config = config::load_cfg_file(&fixture("config_v8.json")).unwrap();
+
+    // the DP-0 monitor moved to another port, the new HDMI-0 has no EDID
+    let mut moved = config.monitors[1].clone();
+    moved.name = "DP-3".to_string();
+    moved.config = None;
+    let mut hdmi = config.monitors[0].clone();
+    hdmi.config = None;
+
+    let monitors = config.assign_stored(vec![moved.clone(), hdmi.clone()]);
+    assert_eq!(monitors[0].config.as_deref(), Some("DP-0"));
+    assert_eq!(monitors[1].config.as_deref(), Some("HDMI-0"));
+
+    // the desk profile refers to the monitor by EDID, so it still matches
+    let profile = config.matching_profile(&[moved.clone(), hdmi]).unwrap();
+    assert_eq!(profile.name, "desk");
+    assert_eq!(profile.assignment(&moved), Some("DP-0"));
+}
+
+#[test]
+fn tells_identical_monitors_apart_by_output() {
+    let config = config::load_cfg_file(&fixture("config_v8.json")).unwrap();
+
+    // a second monitor of the same model without a serial
+    let dp0 = config.monitors[1].clone();
+    let mut twin = dp0.clone();
+    twin.name = "DP-3".to_string();
+    let mut detected = vec![dp0, twin];
+    Monitor::drop_shared_edids(&mut detected);
+    assert!(detected.iter().all(|monitor| monitor.edid.is_none()));
+
+    let monitors = config.assign_stored(detected);
+    assert_eq!(monitors[0].config.as_deref(), Some("DP-0"));
+    assert_eq!(monitors[1].config, None);
+
+    let profile = Profile::from_monitors("twins", &monitors);
+    assert_eq!(profile.outputs, vec!["DP-0", "DP-3"]);
+    assert!(monitors[1].is_known_as("DP-3"));
+    assert!(!monitors[0].is_known_as("DP-3"));
+
+    // a single monitor keeps its identity
+    let mut single = vec![config.monitors[1].clone()];
+    Monitor::drop_shared_edids(&mut single);
+    assert!(single[0].edid.is_some());
+}
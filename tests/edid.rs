@@ -0,0 +1,25 @@
+use lancy_zones::edid::Edid;
+
+fn edid_block() -> Vec<u8> {
+    let mut data = vec![0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+    data.extend([0x10, 0xac, 0xa7, 0xa0, 0x41, 0x30, 0x38, 0x4c]);
+    data.resize(128, 0);
+    data
+}
+
+#[test]
+fn parses_vendor_block() {
+    let edid = Edid::parse(&edid_block()).unwrap();
+    assert_eq!(edid.manufacturer, "DEL");
+    assert_eq!(edid.model, 0xa0a7);
+    assert_eq!(edid.serial, 0x4c383041);
+    assert_eq!(edid.to_string(), "DEL-A0A7-4C383041");
+}
+
+#[test]
+fn rejects_invalid_data() {
+    assert_eq!(Edid::parse(&[]), None);
+    let mut data = edid_block();
+    data[0] = 0xff;
+    assert_eq!(Edid::parse(&data), None);
+}
@@ -0,0 +1,112 @@
+use lancy_zones::{
+    config::Length,
+    layout::{self, CellSpan, LayoutError, Template},
+};
+
+fn rects(zones: &[lancy_zones::config::Zone]) -> Vec<(&str, i16, i16, i16, i16)> {
+    zones
+        .iter()
+        .map(|zone| {
+            let zone_px = zone.resolve(0, 0);
+            (
+                zone.name.as_str(),
+                zone_px.x,
+                zone_px.y,
+                zone_px.width,
+                zone_px.height,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn grid_uses_weights() {
+    let zones = layout::grid(1920, 1080, &[1, 2, 1], &[1, 1], &[]).unwrap();
+    assert_eq!(
+        rects(&zones),
+        vec![
+            ("r1c1", 0, 0, 480, 540),
+            ("r1c2", 480, 0, 960, 540),
+            ("r1c3", 1440, 0, 480, 540),
+            ("r2c1", 0, 540, 480, 540),
+            ("r2c2", 480, 540, 960, 540),
+            ("r2c3", 1440, 540, 480, 540),
+        ]
+    );
+}
+
+#[test]
+fn grid_covers_odd_sizes() {
+    let zones = layout::grid(1000, 100, &[1, 1, 1], &[1], &[]).unwrap();
+    let widths: Vec<Length> = zones.iter().map(|zone| zone.width).collect();
+    assert_eq!(
+        widths,
+        vec![Length::Px(333), Length::Px(334), Length::Px(333)]
+    );
+}
+
+#[test]
+fn grid_merges_cells() {
+    let span: CellSpan = "r1c2:r2c3".parse().unwrap();
+    let zones = layout::grid(300, 200, &[1, 1, 1], &[1, 1], &[span]).unwrap();
+    assert_eq!(
+        rects(&zones),
+        vec![
+            ("r1c1", 0, 0, 100, 100),
+            ("r1c2:r2c3", 100, 0, 200, 200),
+            ("r2c1", 0, 100, 100, 100),
+        ]
+    );
+}
+
+#[test]
+fn grid_rejects_bad_spans() {
+    let outside: CellSpan = "r1c1:r3c1".parse().unwrap();
+    assert_eq!(
+        layout::grid(300, 200, &[1], &[1, 1], &[outside]).unwrap_err(),
+        LayoutError::SpanOutOfBounds(outside)
+    );
+
+    let a: CellSpan = "r1c1:r1c2".parse().unwrap();
+    let b: CellSpan = "r1c2:r2c2".parse().unwrap();
+    assert_eq!(
+        layout::grid(300, 200, &[1, 1], &[1, 1], &[a, b]).unwrap_err(),
+        LayoutError::SpanOverlap(a, b)
+    );
+
+    assert!("r2c2:r1c1".parse::<CellSpan>().is_err());
+    assert!("r0c1:r1c1".parse::<CellSpan>().is_err());
+}
+
+#[test]
+fn templates_expand() {
+    let names = |zones: Vec<lancy_zones::config::Zone>| -> Vec<String> {
+        zones.into_iter().map(|zone| zone.name).collect()
+    };
+
+    let columns = Template::Columns.expand(1920, 1080, Some(2)).unwrap();
+    assert_eq!(
+        rects(&columns),
+        vec![("col1", 0, 0, 960, 1080), ("col2", 960, 0, 960, 1080)]
+    );
+
+    let priority = Template::PriorityGrid.expand(1920, 1080, Some(2)).unwrap();
+    assert_eq!(
+        names(priority),
+        vec!["left1", "main", "right1", "left2", "right2"]
+    );
+
+    let focus = Template::Focus.expand(1000, 1000, None).unwrap();
+    assert_eq!(rects(&focus), vec![("focus", 200, 150, 600, 700)]);
+    assert_eq!(
+        Template::Focus.expand(1000, 1000, Some(1000)).unwrap_err(),
+        LayoutError::InvalidCount(Template::Focus, 1000)
+    );
+
+    assert_eq!(Template::Grid.expand(100, 100, None).unwrap().len(), 4);
+    assert_eq!(
+        Template::Rows.expand(100, 100, Some(0)).unwrap_err(),
+        LayoutError::InvalidCount(Template::Rows, 0)
+    );
+    assert_eq!("priority-grid".parse(), Ok(Template::PriorityGrid));
+}
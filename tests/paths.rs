@@ -0,0 +1,43 @@
+use std::path::{Path, PathBuf};
+
+use lancy_zones::config::{self, CONFIG_PATH_ENV};
+
+fn set(key: &str, value: &str) {
+    // SAFETY: this is the only test in this binary, nothing else reads the environment
+    unsafe { std::env::set_var(key, value) };
+}
+
+#[test]
+fn resolves_config_path_in_order() {
+    set("HOME", "/home/user");
+    set("XDG_CONFIG_HOME", "");
+    set(CONFIG_PATH_ENV, "");
+    assert_eq!(
+        config::resolve_config_path(None),
+        Some(PathBuf::from("/home/user/.config/lancy-zones/config.json"))
+    );
+
+    // relative values are invalid per spec
+    set("XDG_CONFIG_HOME", "relative");
+    assert_eq!(
+        config::resolve_config_path(None),
+        Some(PathBuf::from("/home/user/.config/lancy-zones/config.json"))
+    );
+
+    set("XDG_CONFIG_HOME", "/xdg");
+    assert_eq!(
+        config::resolve_config_path(None),
+        Some(PathBuf::from("/xdg/lancy-zones/config.json"))
+    );
+
+    set(CONFIG_PATH_ENV, "/env/config.json");
+    assert_eq!(
+        config::resolve_config_path(None),
+        Some(PathBuf::from("/env/config.json"))
+    );
+
+    assert_eq!(
+        config::resolve_config_path(Some(Path::new("cli.json"))),
+        Some(PathBuf::from("cli.json"))
+    );
+}
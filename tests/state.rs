@@ -0,0 +1,101 @@
+use lancy_zones::{
+    state::{self, MAX_PLACEMENTS, State, ZoneRef},
+    window::WindowInfo,
+};
+
+fn terminal(title: &str) -> WindowInfo {
+    WindowInfo {
+        instance: "xterm".to_string(),
+        class: "XTerm".to_string(),
+        role: None,
+        title: title.to_string(),
+        window_type: None,
+    }
+}
+
+fn zone(name: &str) -> ZoneRef {
+    ZoneRef {
+        monitor: "DEL-A0A7-4C383041".to_string(),
+        config: "desk".to_string(),
+        name: name.to_string(),
+    }
+}
+
+#[test]
+fn finds_placement_by_title_then_kind() {
+    let mut state = State::default();
+    state.remember(&terminal("vim (1+1)"), zone("left"));
+    state.remember(&terminal("htop"), zone("right"));
+
+    // the title is saved as exact pattern, regex characters included
+    assert_eq!(state.find(&terminal("vim (1+1)")), Some(&zone("left")));
+    assert_eq!(state.find(&terminal("htop")), Some(&zone("right")));
+    // unknown titles fall back to the latest window of the same application
+    assert_eq!(state.find(&terminal("bash")), Some(&zone("right")));
+
+    let mut other_role = terminal("htop");
+    other_role.role = Some("dropdown".to_string());
+    assert_eq!(state.find(&other_role), None);
+
+    // edited patterns match more than one title
+    state.placements[0].title = "^vim".to_string();
+    assert_eq!(state.find(&terminal("vim notes.txt")), Some(&zone("left")));
+}
+
+#[test]
+fn remembering_replaces_and_forgets() {
+    let mut state = State::default();
+    state.remember(&terminal("htop"), zone("left"));
+    state.remember(&terminal("htop"), zone("right"));
+    assert_eq!(state.placements.len(), 1);
+    assert_eq!(state.placements[0].zone, zone("right"));
+
+    state.forget(&terminal("htop"));
+    assert!(state.placements.is_empty());
+
+    for i in 0..MAX_PLACEMENTS + 5 {
+        state.remember(&terminal(&i.to_string()), zone("main"));
+    }
+    assert_eq!(state.placements.len(), MAX_PLACEMENTS);
+    assert_eq!(state.placements[0].title, "^5$");
+}
+
+#[test]
+fn saves_and_loads_state() {
+    let dir = std::env::temp_dir().join(format!("lancy-zones-state-{}", std::process::id()));
+    let path = dir.join("state.json");
+
+    // nothing saved yet
+    assert!(state::load_state_file(&path).unwrap().placements.is_empty());
+
+    let mut saved = State::default();
+    saved.remember(&terminal("htop"), zone("main"));
+    state::save_state_file(&path, &saved).unwrap();
+    let loaded = state::load_state_file(&path).unwrap();
+    assert_eq!(loaded.placements, saved.placements);
+
+    std::fs::write(&path, "{").unwrap();
+    let e = state::load_state_file(&path).unwrap_err();
+    assert!(e.to_string().starts_with("could not parse state file"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn parses_window_properties() {
+    assert_eq!(
+        WindowInfo::parse_class(b"xterm\0XTerm\0"),
+        ("xterm".to_string(), "XTerm".to_string())
+    );
+    assert_eq!(WindowInfo::parse_class(b""), (String::new(), String::new()));
+
+    let zone = zone("main");
+    assert_eq!(ZoneRef::from_property(&zone.to_property()), Some(zone));
+    assert_eq!(ZoneRef::from_property(b"no separator"), None);
+
+    // zones recorded without their layout follow the one the monitor shows
+    let legacy = ZoneRef::from_property(b"DP-0\0main").unwrap();
+    assert_eq!((legacy.config.as_str(), legacy.name.as_str()), ("", "main"));
+    let saved: ZoneRef = serde_json::from_str(r#"{"monitor":"DP-0","name":"main"}"#).unwrap();
+    assert_eq!(saved, legacy);
+}
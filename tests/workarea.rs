@@ -0,0 +1,51 @@
+use lancy_zones::workarea::{Area, Strut, work_area};
+
+const LEFT: Area = Area {
+    x: 0,
+    y: 0,
+    width: 1920,
+    height: 1080,
+};
+const RIGHT: Area = Area {
+    x: 1920,
+    y: 0,
+    width: 2560,
+    height: 1440,
+};
+
+#[test]
+fn top_panel_only_shrinks_its_monitor() {
+    // 30px panel along the top of the left monitor
+    let panel = Strut::from_partial(&[0, 0, 30, 0, 0, 0, 0, 0, 0, 1919, 0, 0]).unwrap();
+    assert_eq!(
+        work_area(LEFT, 4480, 1440, &[panel]),
+        Area {
+            y: 30,
+            height: 1050,
+            ..LEFT
+        }
+    );
+    assert_eq!(work_area(RIGHT, 4480, 1440, &[panel]), RIGHT);
+}
+
+#[test]
+fn full_struts_span_every_monitor() {
+    let dock = Strut::from_full(&[0, 48, 0, 0], 4480, 1440).unwrap();
+    assert_eq!(work_area(LEFT, 4480, 1440, &[dock]), LEFT);
+    assert_eq!(
+        work_area(RIGHT, 4480, 1440, &[dock]),
+        Area {
+            width: 2512,
+            ..RIGHT
+        }
+    );
+}
+
+#[test]
+fn bottom_strut_below_a_shorter_monitor() {
+    // panel along the bottom of the taller right monitor, 1440 - 40
+    let panel = Strut::from_partial(&[0, 0, 0, 40, 0, 0, 0, 0, 0, 0, 1920, 4479]).unwrap();
+    assert_eq!(work_area(LEFT, 4480, 1440, &[panel]), LEFT);
+    assert_eq!(work_area(RIGHT, 4480, 1440, &[panel]).height, 1400);
+    assert_eq!(Strut::from_partial(&[1, 2, 3]), None);
+}